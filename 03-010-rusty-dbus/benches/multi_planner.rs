@@ -0,0 +1,75 @@
+//! Benchmarks of the multi-agent planner on crowded maps.
+//!
+//! Robots start on the western and southern border, alternately, and cross
+//! the map to the opposite border, so that their routes run into each other.
+//! Run with `cargo bench`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rusty_the_robot::planner::multi::{MultiPlanner, Task};
+use rusty_the_robot::{Position, Tile, World};
+use std::hint::black_box;
+
+/// Width and height of the maps.
+const SIZE: i32 = 16;
+
+/// Adds `robots` robots, at most `2 * (SIZE - 2)`, and returns their tasks.
+fn crossing(mut world: World, robots: i32) -> (World, Vec<Task>) {
+    let mut tasks = Vec::new();
+    for index in 0..robots {
+        let name = format!("robot{index}");
+        let lane = 1 + index / 2;
+        let (start, goal) = if index % 2 == 0 {
+            (Position::new(0, lane), Position::new(SIZE - 1, lane))
+        } else {
+            (Position::new(lane, 0), Position::new(lane, SIZE - 1))
+        };
+        world.add_robot_new(name.clone());
+        if let Some(robot) = world.get_robot_mut(&name) {
+            robot.position = start;
+        }
+        tasks.push(Task { name, goal });
+    }
+    (world, tasks)
+}
+
+/// No obstacles at all.
+fn open(robots: i32) -> (World, Vec<Task>) {
+    crossing(World::new(SIZE as u32, SIZE as u32), robots)
+}
+
+/// Rows of shelves with aisles in between, crossed by one aisle in the middle.
+fn warehouse(robots: i32) -> (World, Vec<Task>) {
+    let mut world = World::new(SIZE as u32, SIZE as u32);
+    for x in (3..SIZE - 2).step_by(2) {
+        for y in (1..SIZE - 1).filter(|&y| y != SIZE / 2) {
+            world.add_tile(Position::new(x, y), Tile::Wall);
+        }
+    }
+    crossing(world, robots)
+}
+
+fn bench(c: &mut Criterion) {
+    let planner = MultiPlanner::default();
+    let mut group = c.benchmark_group("multi_planner");
+    group.sample_size(10);
+    for (map, build) in [
+        ("open", open as fn(i32) -> (World, Vec<Task>)),
+        ("warehouse", warehouse),
+    ] {
+        for robots in [4, 8, 16, 28] {
+            let (world, tasks) = build(robots);
+            let solved = planner.plan(&world, &tasks);
+            assert!(
+                solved.is_ok_and(|schedule| schedule.is_collision_free()),
+                "no plans for {robots} robots on the {map} map"
+            );
+            group.bench_with_input(BenchmarkId::new(map, robots), &robots, |b, _| {
+                b.iter(|| black_box(planner.plan(&world, &tasks)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -1,5 +1,9 @@
 //! Command line arguments and commands to start and control the robot and world.
-use clap::Parser;
+use crate::generate::Layout;
+use crate::world::MovePolicy;
+use crate::world::storage::Format;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 /// Command-line interface for the Rusty Robot world builder.
 ///
@@ -15,13 +19,37 @@ use clap::Parser;
 ///
 /// # Use short flag for name
 /// cargo run -- -n boris
+///
+/// # Start from a text map
+/// cargo run -- --map arenas/two_rooms.txt
+///
+/// # Run a robot program on the saved world and exit
+/// cargo run -- run-program programs/wall_follow.rusty
 /// ```
 ///
 /// # Fields
 ///
 /// - `name`: The name of the robot to be added to the world.
+/// - `ghost`: Whether the robot may overlap with other robots.
 /// - `height`: The vertical size (in tiles) of the generated world.
 /// - `width`: The horizontal size (in tiles) of the generated world.
+/// - `move_policy`: What happens when a move runs into a wall or the border.
+/// - `tick_ms`: Duration of one simulation tick.
+/// - `world`: Path of the world file.
+/// - `format`: Format of the world file instead of the one of its extension.
+/// - `ticks`: Run a fixed number of ticks headless instead of serving D-Bus.
+/// - `force_new`: Start a new world if the world file cannot be loaded.
+/// - `program`: JSON or TOML program queued for the robot `name` on start.
+/// - `controller`: Rhai script controlling the robot `name`.
+/// - `script_budget`: Operations a controller script may run per tick.
+/// - `explore`: Let the robot `name` explore the world on its own.
+/// - `cover`: Let the robot `name` visit every reachable free cell.
+/// - `map`: Text map the world is read from instead of the world file.
+/// - `generate`: Generate a new world of this layout instead of loading one.
+/// - `seed`: Seed of the generator, random if not given.
+/// - `charge_pads`: Charge pads scattered over a generated world.
+/// - `migrate`: World files to upgrade to the current format instead of running.
+/// - `action`: Optional subcommand to run instead of the service.
 ///
 #[derive(Parser, Debug)]
 #[command(
@@ -42,25 +70,276 @@ pub struct Cli {
     #[arg(short, long, default_value = "rusty")]
     pub name: String,
 
+    /// Let the robot overlap with other robots (for debugging).
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name casper --ghost
+    /// ```
+    #[arg(long)]
+    pub ghost: bool,
+
     /// Height (number of tiles) of the world.
     ///
-    /// Defaults to `20`.
+    /// Defaults to `20`, must be at least `1`.
     ///
     /// # Example
     /// ```bash
     /// cargo run -- --height 25
     /// ```
-    #[arg(long, default_value_t = 20)]
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
     pub height: u32,
 
     /// Width (number of tiles) of the world.
     ///
-    /// Defaults to `40`.
+    /// Defaults to `40`, must be at least `1`.
     ///
     /// # Example
     /// ```bash
     /// cargo run -- --width 50
     /// ```
-    #[arg(long, default_value_t = 40)]
+    #[arg(long, default_value_t = 40, value_parser = clap::value_parser!(u32).range(1..))]
     pub width: u32,
+
+    /// What to do when a move hits a wall or leaves the world.
+    ///
+    /// Overrides the policy stored in a loaded world if given.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --move-policy reject-whole
+    /// ```
+    #[arg(long, value_enum)]
+    pub move_policy: Option<MovePolicy>,
+
+    /// Duration of one simulation tick in milliseconds.
+    ///
+    /// Navigating robots move one step per tick and robots on a
+    /// charge pad recharge once per tick.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --tick-ms 100
+    /// ```
+    #[arg(long, default_value_t = 200)]
+    pub tick_ms: u64,
+
+    /// File the world is loaded from and saved to on shutdown.
+    ///
+    /// Defaults to `world.json`.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --world arena.json
+    /// ```
+    #[arg(short, long, default_value = "world.json")]
+    pub world: PathBuf,
+
+    /// Format of the world file, and of the files of `--migrate` and `check`.
+    ///
+    /// Taken from the file extension if not given: `.json`, `.toml`, `.ron`,
+    /// `.msgpack` or `.mpk` and `.cbor`; anything else is JSON.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --world arena.bin --format cbor
+    /// ```
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<Format>,
+
+    /// Start a new world if the world file exists but cannot be loaded.
+    ///
+    /// Without it the program refuses to start rather than overwrite a
+    /// broken file on shutdown, also if `--map` or `--generate` replace
+    /// the world anyway.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --world broken.json --force-new
+    /// ```
+    #[arg(long)]
+    pub force_new: bool,
+
+    /// Run this many ticks as fast as possible without D-Bus, then save and exit.
+    ///
+    /// The outcome only depends on the loaded world, which makes it
+    /// suitable for tests.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --ticks 100
+    /// ```
+    #[arg(long)]
+    pub ticks: Option<u64>,
+
+    /// Program file (`.json` or `.toml`) whose commands are queued for the robot on start.
+    ///
+    /// The robot works the commands off one per tick.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name rusty --program patrol.toml
+    /// ```
+    #[arg(long)]
+    pub program: Option<PathBuf>,
+
+    /// Rhai script (`.rhai`) that controls the robot whenever it has no commands left.
+    ///
+    /// Stored in the world, so the robot keeps it after a restart.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name rusty --controller programs/bounce.rhai
+    /// ```
+    #[arg(long)]
+    pub controller: Option<PathBuf>,
+
+    /// Operations a controller script may execute per tick before it is stopped.
+    ///
+    /// Defaults to `10000`.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --script-budget 500
+    /// ```
+    #[arg(long, default_value_t = crate::controller::DEFAULT_BUDGET)]
+    pub script_budget: u64,
+
+    /// Let the robot explore the world on its own until its belief map is complete.
+    ///
+    /// Coverage is reported as it goes; see also the `Explore` D-Bus method.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name rusty --explore --ticks 500
+    /// ```
+    #[arg(long)]
+    pub explore: bool,
+
+    /// Let the robot visit every free cell it can reach, like a vacuum robot.
+    ///
+    /// Replaces queued commands with a planned route and logs how well it
+    /// covers the world; with `--ticks` also how well the robot did.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name rusty --cover --ticks 500
+    /// ```
+    #[arg(long)]
+    pub cover: bool,
+
+    /// Text map (see `arenas/`) to start from instead of the world file.
+    ///
+    /// The world is still saved to the world file on shutdown.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --map arenas/two_rooms.txt
+    /// ```
+    #[arg(long, conflicts_with = "generate")]
+    pub map: Option<PathBuf>,
+
+    /// Generate a new `width` x `height` world of this layout, even if the world file exists.
+    ///
+    /// Every free cell of a generated world is reachable.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --generate maze --seed 42
+    /// ```
+    #[arg(long, value_enum)]
+    pub generate: Option<Layout>,
+
+    /// Seed of `--generate`; the same seed always gives the same world.
+    ///
+    /// A random seed is used and logged if not given.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --generate caves --seed 7
+    /// ```
+    #[arg(long, requires = "generate")]
+    pub seed: Option<u64>,
+
+    /// Number of charge pads scattered over a generated world.
+    ///
+    /// Defaults to `2`.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --generate rooms --charge-pads 4
+    /// ```
+    #[arg(long, default_value_t = 2)]
+    pub charge_pads: usize,
+
+    /// Upgrade these world files in place to the current format version and exit.
+    ///
+    /// Files already up to date are left untouched. Problems of the worlds
+    /// are logged, the files are upgraded all the same.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --migrate world.json arena.json
+    /// ```
+    #[arg(long, num_args = 1.., value_name = "FILE")]
+    pub migrate: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    pub action: Option<Action>,
+}
+
+/// One-shot tasks run instead of serving the world on D-Bus.
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// Run a program in the robot language on robot `name`, then save the world.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- --name rusty run-program programs/wall_follow.rusty
+    /// ```
+    RunProgram {
+        /// Source file of the program.
+        file: PathBuf,
+    },
+    /// Write the world as a text map, readable with `--map`, and exit.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- export-map arenas/saved.txt
+    /// ```
+    ExportMap {
+        /// File the map is written to.
+        file: PathBuf,
+    },
+    /// Print the problems of a world file and fail if there are any.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- check world.json
+    /// ```
+    Check {
+        /// The world file to check.
+        file: PathBuf,
+    },
+    /// Read a world file and write it in another format, e.g. compact CBOR
+    /// for large worlds or TOML to edit by hand.
+    ///
+    /// The formats follow the file extensions unless given.
+    ///
+    /// # Example
+    /// ```bash
+    /// cargo run -- convert world.json world.cbor
+    /// ```
+    Convert {
+        /// The world file to read.
+        input: PathBuf,
+        /// The file to write.
+        output: PathBuf,
+        /// Format of `input`.
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        /// Format of `output`.
+        #[arg(long, value_enum)]
+        to: Option<Format>,
+    },
 }
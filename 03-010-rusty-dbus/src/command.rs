@@ -0,0 +1,203 @@
+//! Commands a robot executes one per tick, and programs made of them.
+//!
+//! Every [`crate::robot::Robot`] owns a queue of [`crate::command::Command`]s
+//! that [`crate::world::World::step`] works off in order. A
+//! [`crate::command::Program`] is a list of commands loaded from a JSON or
+//! TOML file, e.g. a patrol routine.
+
+use crate::moveable::Direction;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// A single entry of a robot's command queue.
+///
+/// In program files every command is a table with a `command` key, e.g.
+/// `{ "command": "forward", "step": 2 }` or `{ "command": "wait", "ticks": 3 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Move forward by `step` cells, see [`Direction::Forward`].
+    Forward { step: i32 },
+    /// Move one cell backward.
+    Backwards,
+    /// Move one cell to the left.
+    Left,
+    /// Move one cell to the right.
+    Right,
+    /// Turn a quarter to the left.
+    TurnLeft,
+    /// Turn a quarter to the right.
+    TurnRight,
+    /// Do nothing for `ticks` ticks.
+    Wait { ticks: u32 },
+    /// Stay on a charge pad until the battery is full.
+    ChargeUntilFull,
+}
+
+impl Command {
+    /// The movement of this command, `None` for commands that do not move.
+    pub fn direction(&self) -> Option<Direction> {
+        match *self {
+            Command::Forward { step } => Some(Direction::Forward { step }),
+            Command::Backwards => Some(Direction::Backwards),
+            Command::Left => Some(Direction::Left),
+            Command::Right => Some(Direction::Right),
+            Command::TurnLeft => Some(Direction::TurnLeft),
+            Command::TurnRight => Some(Direction::TurnRight),
+            Command::Wait { .. } | Command::ChargeUntilFull => None,
+        }
+    }
+}
+
+impl From<Direction> for Command {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Forward { step } => Command::Forward { step },
+            Direction::Backwards => Command::Backwards,
+            Direction::Left => Command::Left,
+            Direction::Right => Command::Right,
+            Direction::TurnLeft => Command::TurnLeft,
+            Direction::TurnRight => Command::TurnRight,
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Forward { step } => write!(f, "forward {step}"),
+            Command::Backwards => write!(f, "backwards"),
+            Command::Left => write!(f, "left"),
+            Command::Right => write!(f, "right"),
+            Command::TurnLeft => write!(f, "turn_left"),
+            Command::TurnRight => write!(f, "turn_right"),
+            Command::Wait { ticks } => write!(f, "wait {ticks}"),
+            Command::ChargeUntilFull => write!(f, "charge_until_full"),
+        }
+    }
+}
+
+/// Errors while loading a [`Program`].
+#[derive(Debug)]
+pub enum ProgramError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file content is not a valid program.
+    Parse(String),
+    /// The file extension is neither `json` nor `toml`.
+    UnsupportedFormat(String),
+}
+
+/// A list of commands to queue on a robot.
+///
+/// # Example
+/// ```toml
+/// [[commands]]
+/// command = "forward"
+/// step = 3
+///
+/// [[commands]]
+/// command = "turn_right"
+///
+/// [[commands]]
+/// command = "wait"
+/// ticks = 5
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub commands: Vec<Command>,
+}
+
+impl Program {
+    /// Parses a program in JSON format.
+    pub fn from_json(data: &str) -> Result<Self, ProgramError> {
+        serde_json::from_str(data).map_err(|e| ProgramError::Parse(e.to_string()))
+    }
+
+    /// Parses a program in TOML format.
+    pub fn from_toml(data: &str) -> Result<Self, ProgramError> {
+        toml::from_str(data).map_err(|e| ProgramError::Parse(e.to_string()))
+    }
+
+    /// Loads a program from `path`, the format is chosen by the extension.
+    pub fn load(path: &Path) -> Result<Self, ProgramError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let parse = match extension.as_str() {
+            "json" => Self::from_json,
+            "toml" => Self::from_toml,
+            _ => return Err(ProgramError::UnsupportedFormat(extension)),
+        };
+        let data = std::fs::read_to_string(path).map_err(ProgramError::Io)?;
+        parse(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patrol() -> Program {
+        Program {
+            commands: vec![
+                Command::Forward { step: 3 },
+                Command::TurnRight,
+                Command::Wait { ticks: 5 },
+                Command::ChargeUntilFull,
+            ],
+        }
+    }
+
+    #[test]
+    fn program_from_json() -> Result<(), ProgramError> {
+        let program = Program::from_json(
+            r#"{"commands": [
+                {"command": "forward", "step": 3},
+                {"command": "turn_right"},
+                {"command": "wait", "ticks": 5},
+                {"command": "charge_until_full"}
+            ]}"#,
+        )?;
+        assert_eq!(program, patrol());
+        Ok(())
+    }
+
+    #[test]
+    fn program_from_toml() -> Result<(), ProgramError> {
+        let program = Program::from_toml(
+            r#"
+            [[commands]]
+            command = "forward"
+            step = 3
+
+            [[commands]]
+            command = "turn_right"
+
+            [[commands]]
+            command = "wait"
+            ticks = 5
+
+            [[commands]]
+            command = "charge_until_full"
+            "#,
+        )?;
+        assert_eq!(program, patrol());
+        Ok(())
+    }
+
+    #[test]
+    fn program_rejects_unknown_commands() {
+        assert!(matches!(
+            Program::from_json(r#"{"commands": [{"command": "fly"}]}"#),
+            Err(ProgramError::Parse(_))
+        ));
+        assert!(matches!(
+            Program::load(Path::new("patrol.yaml")),
+            Err(ProgramError::UnsupportedFormat(_))
+        ));
+    }
+}
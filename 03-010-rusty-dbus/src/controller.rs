@@ -0,0 +1,453 @@
+//! Robot controllers written in [Rhai](https://rhai.rs).
+//!
+//! A robot with a [`crate::robot::Robot::controller`] script runs it on
+//! every tick its command queue is empty. The script sees the robot as the
+//! variable `robot` and decides what to do next:
+//!
+//! ```text
+//! if robot.tile_ahead() == "empty" {
+//!     robot.forward(1);
+//! } else {
+//!     robot.turn_right();
+//! }
+//! ```
+//!
+//! # API of `robot`
+//!
+//! - `name`, `x`, `y`, `heading` (`"N"`, `"E"`, `"S"` or `"W"`), `battery`
+//!   (0 to 255), `tick`
+//! - `tile(dx, dy)` and `tile_ahead()` name a nearby cell: `"empty"`,
+//!   `"wall"`, `"charge_pad"`, `"open_door"`, `"closed_door"`, `"robot"` or
+//!   `"outside"`. Cells further than [`crate::controller::SENSOR_RANGE`] away
+//!   are `"unknown"`. `dx` grows to the east, `dy` to the north.
+//! - `forward(steps)`, `backwards()`, `left()`, `right()`, `turn_left()`,
+//!   `turn_right()`, `wait(ticks)` and `charge()` queue
+//!   [`crate::command::Command`]s
+//! - `scan()` returns the readings of the robot's lidar, see
+//!   [`crate::sensor`], as maps with `angle`, `distance` and `hit`
+//!   (`"wall"`, `"robot"` or `"nothing"`), plus `robot` with the name of a
+//!   hit robot
+//! - `memory`, an object map kept between ticks
+//!
+//! Scripts cannot touch the world directly, read files or import modules.
+//! Each run may execute at most the configured number of operations, a
+//! runaway script is stopped and reported instead of stalling the
+//! simulation. Strings, arrays, maps and the call depth are limited as well,
+//! so that a script cannot use up the service's memory within its budget.
+
+use crate::command::Command;
+use crate::heading::Heading;
+use crate::position::Position;
+use crate::robot::Robot;
+use crate::sensor::Hit;
+use crate::simulation::WorldEvent;
+use crate::world::{Tile, World};
+use log::{info, warn};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, Array, Dynamic, Engine, FLOAT, INT, Map, Scope};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Operations a script may execute per tick by default.
+pub const DEFAULT_BUDGET: u64 = 10_000;
+
+/// How far, in cells along each axis, a script can see.
+pub const SENSOR_RANGE: i32 = 2;
+
+/// Longest string, in bytes, a script may build.
+pub const MAX_STRING_SIZE: usize = 10_000;
+
+/// Most elements an array or object map of a script may hold, `memory` included.
+pub const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// How deep function calls of a script may nest.
+pub const MAX_CALL_LEVELS: usize = 32;
+
+/// What a script sees of its robot and collects the commands it issues.
+#[derive(Debug, Clone)]
+struct RobotView {
+    name: String,
+    position: Position,
+    heading: Heading,
+    battery: u8,
+    tick: u64,
+    /// Names of the cells within [`SENSOR_RANGE`], by offset.
+    nearby: HashMap<(i32, i32), &'static str>,
+    /// Lidar readings taken when the view was created.
+    scan: Array,
+    memory: Map,
+    commands: Vec<Command>,
+}
+
+impl RobotView {
+    fn new(world: &World, robot: &Robot, tick: u64, memory: Map) -> Self {
+        let mut nearby = HashMap::new();
+        for dx in -SENSOR_RANGE..=SENSOR_RANGE {
+            for dy in -SENSOR_RANGE..=SENSOR_RANGE {
+                let position = Position::new(robot.position.x + dx, robot.position.y + dy);
+                nearby.insert((dx, dy), tile_name(world, robot, &position));
+            }
+        }
+        let scan = robot
+            .lidar
+            .scan(world, &robot.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|reading| {
+                let mut map = Map::new();
+                map.insert("angle".into(), Dynamic::from(reading.angle as FLOAT));
+                map.insert("distance".into(), Dynamic::from(reading.distance as FLOAT));
+                map.insert("hit".into(), reading.hit.kind().into());
+                let other = match reading.hit {
+                    Hit::Robot(name) => name,
+                    _ => String::new(),
+                };
+                map.insert("robot".into(), other.into());
+                Dynamic::from_map(map)
+            })
+            .collect();
+        Self {
+            name: robot.name.clone(),
+            position: robot.position.clone(),
+            heading: robot.heading,
+            battery: robot.state_of_charge,
+            tick,
+            nearby,
+            scan,
+            memory,
+            commands: Vec::new(),
+        }
+    }
+
+    fn tile(&mut self, dx: INT, dy: INT) -> String {
+        let offset = (i32::try_from(dx), i32::try_from(dy));
+        let name = match offset {
+            (Ok(dx), Ok(dy)) => self.nearby.get(&(dx, dy)).copied(),
+            _ => None,
+        };
+        name.unwrap_or("unknown").to_string()
+    }
+
+    fn tile_ahead(&mut self) -> String {
+        let (dx, dy) = self.heading.delta();
+        self.tile(INT::from(dx), INT::from(dy))
+    }
+
+    fn queue(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+}
+
+fn tile_name(world: &World, robot: &Robot, position: &Position) -> &'static str {
+    if !world.in_bounds(position) {
+        return "outside";
+    }
+    if world
+        .occupant(position)
+        .is_some_and(|other| other.name != robot.name)
+    {
+        return "robot";
+    }
+    match world.tile(position) {
+        None | Some(Tile::Empty) => "empty",
+        Some(Tile::Wall) => "wall",
+        Some(Tile::ChargePad) => "charge_pad",
+        Some(Tile::Door { open: true, .. }) => "open_door",
+        Some(Tile::Door { open: false, .. }) => "closed_door",
+    }
+}
+
+fn engine(budget: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(budget);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|text| info!("script: {text}"));
+    engine.on_debug(|text, _, position| info!("script at {position}: {text}"));
+
+    engine
+        .register_type_with_name::<RobotView>("Robot")
+        .register_get("name", |view: &mut RobotView| view.name.clone())
+        .register_get("x", |view: &mut RobotView| INT::from(view.position.x))
+        .register_get("y", |view: &mut RobotView| INT::from(view.position.y))
+        .register_get("heading", |view: &mut RobotView| view.heading.to_string())
+        .register_get("battery", |view: &mut RobotView| INT::from(view.battery))
+        .register_get("tick", |view: &mut RobotView| view.tick as INT)
+        .register_get_set(
+            "memory",
+            |view: &mut RobotView| view.memory.clone(),
+            |view: &mut RobotView, memory: Map| view.memory = memory,
+        )
+        .register_fn("tile", RobotView::tile)
+        .register_fn("tile_ahead", RobotView::tile_ahead)
+        .register_fn("scan", |view: &mut RobotView| view.scan.clone())
+        .register_fn("forward", |view: &mut RobotView, steps: INT| {
+            let step = i32::try_from(steps).unwrap_or(i32::MAX);
+            view.queue(Command::Forward { step });
+        })
+        .register_fn("backwards", |view: &mut RobotView| {
+            view.queue(Command::Backwards)
+        })
+        .register_fn("left", |view: &mut RobotView| view.queue(Command::Left))
+        .register_fn("right", |view: &mut RobotView| view.queue(Command::Right))
+        .register_fn("turn_left", |view: &mut RobotView| {
+            view.queue(Command::TurnLeft)
+        })
+        .register_fn("turn_right", |view: &mut RobotView| {
+            view.queue(Command::TurnRight)
+        })
+        .register_fn("wait", |view: &mut RobotView, ticks: INT| {
+            let ticks = u32::try_from(ticks).unwrap_or_default();
+            view.queue(Command::Wait { ticks });
+        })
+        .register_fn("charge", |view: &mut RobotView| {
+            view.queue(Command::ChargeUntilFull)
+        });
+    engine
+}
+
+/// A compiled controller script, or why it could not be compiled.
+struct Controller {
+    path: PathBuf,
+    script: Result<AST, String>,
+    memory: Map,
+}
+
+/// Runs the controller scripts of all robots.
+pub struct Controllers {
+    engine: Engine,
+    controllers: HashMap<String, Controller>,
+}
+
+impl Default for Controllers {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET)
+    }
+}
+
+impl Controllers {
+    /// Creates the script engine, allowing `budget` operations per script and tick.
+    pub fn new(budget: u64) -> Self {
+        Self {
+            engine: engine(budget),
+            controllers: HashMap::new(),
+        }
+    }
+
+    fn compile(&self, path: &Path) -> Result<AST, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        self.engine
+            .compile(source)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Runs the controller of every idle robot that has one.
+    ///
+    /// Scripts are compiled when first seen or when the robot's script path
+    /// changed. The commands a script issues are queued on its robot.
+    /// Scripts that do not compile, fail or exceed the operation budget are
+    /// reported as [`WorldEvent::ScriptFailed`]; a broken script is only
+    /// reported once until it is attached again.
+    pub fn run(&mut self, world: &mut World, tick: u64) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        let attached: Vec<(String, PathBuf)> = world
+            .robots()
+            .iter()
+            .filter_map(|robot| Some((robot.name.clone(), robot.controller.clone()?)))
+            .collect();
+        self.controllers
+            .retain(|name, _| attached.iter().any(|(robot, _)| robot == name));
+
+        for (name, path) in attached {
+            if self
+                .controllers
+                .get(&name)
+                .is_none_or(|controller| controller.path != path)
+            {
+                let script = self.compile(&path);
+                if let Err(error) = &script {
+                    events.push(WorldEvent::ScriptFailed {
+                        name: name.clone(),
+                        error: error.clone(),
+                    });
+                }
+                let controller = Controller {
+                    path,
+                    script,
+                    memory: Map::new(),
+                };
+                self.controllers.insert(name.clone(), controller);
+            }
+            let Some(controller) = self.controllers.get_mut(&name) else {
+                continue;
+            };
+            let Ok(script) = &controller.script else {
+                continue;
+            };
+            let Some(robot) = world.get_robot(&name).filter(|robot| robot.commands.is_empty())
+            else {
+                continue;
+            };
+
+            let view = RobotView::new(world, robot, tick, std::mem::take(&mut controller.memory));
+            let mut scope = Scope::new();
+            scope.push("robot", view);
+            let result = self.engine.run_ast_with_scope(&mut scope, script);
+            let Some(view) = scope.get_value::<RobotView>("robot") else {
+                continue;
+            };
+            controller.memory = view.memory;
+            match result {
+                Ok(()) => {
+                    for command in view.commands {
+                        let _ = world.queue_command(&name, command);
+                    }
+                }
+                Err(error) => {
+                    warn!("Controller of {name} failed: {error}");
+                    events.push(WorldEvent::ScriptFailed {
+                        name,
+                        error: error.to_string(),
+                    });
+                }
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A script file unique to the calling test, deleted when dropped.
+    struct ScriptFile(PathBuf);
+
+    impl Drop for ScriptFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Writes `source` to a script file unique to the calling test.
+    fn script(test: &str, source: &str) -> std::io::Result<ScriptFile> {
+        let path = std::env::temp_dir().join(format!("rusty-{}-{test}.rhai", std::process::id()));
+        std::fs::write(&path, source)?;
+        Ok(ScriptFile(path))
+    }
+
+    fn world(controller: &ScriptFile) -> World {
+        let mut world = World::new(5, 5);
+        world.add_robot_new(String::from("rusty"));
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            robot.controller = Some(controller.0.clone());
+        }
+        world
+    }
+
+    #[test]
+    fn run_queues_commands_of_idle_robots() -> std::io::Result<()> {
+        let path = script(
+            "idle",
+            r#"
+            robot.memory.runs = (robot.memory.runs ?? 0) + 1;
+            if robot.tile_ahead() == "empty" { robot.forward(2); } else { robot.turn_right(); }
+            if robot.memory.runs == 2 { robot.wait(3); }
+            "#,
+        )?;
+        let mut world = world(&path);
+        let mut controllers = Controllers::default();
+
+        assert!(controllers.run(&mut world, 1).is_empty());
+        world.step(1);
+        assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 2)));
+        assert!(controllers.run(&mut world, 2).is_empty());
+        assert_eq!(
+            world.get_robot("rusty").map(|robot| robot.commands.clone()),
+            Some([Command::Forward { step: 2 }, Command::Wait { ticks: 3 }].into())
+        );
+        // busy robots are left alone
+        controllers.run(&mut world, 3);
+        assert_eq!(
+            world.get_robot("rusty").map(|robot| robot.commands.len()),
+            Some(2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn run_exposes_the_lidar() -> std::io::Result<()> {
+        let path = script(
+            "lidar",
+            r#"
+            let ahead = robot.scan()[0];
+            if ahead.hit == "wall" && ahead.distance == 4.5 { robot.turn_left(); }
+            "#,
+        )?;
+        let mut world = world(&path);
+        assert!(Controllers::default().run(&mut world, 1).is_empty());
+        assert_eq!(
+            world.get_robot("rusty").map(|robot| robot.commands.clone()),
+            Some([Command::TurnLeft].into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn run_stops_runaway_scripts() -> std::io::Result<()> {
+        let path = script("runaway", "robot.turn_left(); loop { }")?;
+        let mut world = world(&path);
+        let mut controllers = Controllers::new(1_000);
+        let events = controllers.run(&mut world, 1);
+        assert!(matches!(
+            events.as_slice(),
+            [WorldEvent::ScriptFailed { name, error }]
+                if name == "rusty" && error.contains("Too many operations")
+        ));
+        // commands of a failed run are dropped
+        assert_eq!(
+            world.get_robot("rusty").map(|robot| robot.commands.len()),
+            Some(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn run_limits_the_memory_of_scripts() -> std::io::Result<()> {
+        // each of them runs out of memory long before the unlimited budget
+        let sources = [
+            ("string", r#"let s = "rusty"; loop { s += s; }"#, "Length of string"),
+            ("array", "let a = [0]; loop { a += a; }", "Size of array"),
+            ("map", "let m = #{}; loop { m = #{ a: m, b: m }; }", "Size of object map"),
+            ("calls", "fn deeper(n) { deeper(n + 1) } deeper(0);", "Stack overflow"),
+        ];
+        for (test, source, expected) in sources {
+            let path = script(test, source)?;
+            let mut world = world(&path);
+            let events = Controllers::new(u64::MAX).run(&mut world, 1);
+            assert!(
+                matches!(
+                    events.as_slice(),
+                    [WorldEvent::ScriptFailed { error, .. }] if error.starts_with(expected)
+                ),
+                "{test}: {events:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn run_reports_broken_scripts_once() -> std::io::Result<()> {
+        let path = script("broken", "robot.forward(1")?;
+        let mut world = world(&path);
+        let mut controllers = Controllers::default();
+        assert_eq!(controllers.run(&mut world, 1).len(), 1);
+        assert!(controllers.run(&mut world, 2).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Coverage path planning for cleaning-style robots.
+//!
+//! [`crate::coverage::plan`] finds a route that visits every reachable
+//! coverable cell, that is every [`crate::world::Tile::Empty`] and unset cell.
+//! It sweeps the world boustrophedon style, column by column, by always
+//! trying to go north, south, east and west in that order. At a dead end it
+//! takes the shortest way to the nearest cell not covered yet and sweeps on
+//! from there (like BA*). Charge pads may be crossed but need no cleaning;
+//! walls, doors, which might close on the way, and parked robots are avoided.
+//!
+//! [`crate::coverage::Visits`] tracks the cells a robot actually entered.
+//! Routes and visits are rated with a [`crate::coverage::CoverageReport`].
+
+use crate::planner::{Path, PlanError};
+use crate::position::Position;
+use crate::world::{Tile, World};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Order in which the sweep tries the neighbours of a cell.
+const SWEEP: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+
+/// Whether a route may lead over `position`.
+fn passable(world: &World, position: &Position) -> bool {
+    world.in_bounds(position)
+        && !world.is_blocked(position)
+        && !matches!(world.tile(position), Some(Tile::Door { .. }))
+}
+
+/// Whether `position` has to be covered.
+fn coverable(world: &World, position: &Position) -> bool {
+    passable(world, position) && matches!(world.tile(position), None | Some(Tile::Empty))
+}
+
+fn neighbours(cell: &Position) -> impl Iterator<Item = Position> + '_ {
+    SWEEP
+        .iter()
+        .map(move |(dx, dy)| Position::new(cell.x + dx, cell.y + dy))
+}
+
+/// Shortest route over `clear` cells from `start` to the nearest cell in `goals`,
+/// without `start` itself.
+fn nearest(
+    clear: impl Fn(&Position) -> bool,
+    start: &Position,
+    goals: &HashSet<Position>,
+) -> Option<Vec<Position>> {
+    let mut came_from: HashMap<Position, Position> = HashMap::new();
+    let mut open = VecDeque::from([start.clone()]);
+    while let Some(cell) = open.pop_front() {
+        if goals.contains(&cell) {
+            let mut route = vec![cell];
+            while let Some(previous) = route.last().and_then(|cell| came_from.get(cell)) {
+                if previous == start {
+                    break;
+                }
+                route.push(previous.clone());
+            }
+            route.reverse();
+            return Some(route);
+        }
+        for next in neighbours(&cell) {
+            if next != *start && !came_from.contains_key(&next) && clear(&next) {
+                came_from.insert(next.clone(), cell.clone());
+                open.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Plans a route from `start` visiting every coverable cell reachable from there.
+///
+/// Cells of other robots are treated as blocked, they count as not covered.
+///
+/// The returned [`Path`] costs `1.0` per step and can be turned into
+/// [`crate::moveable::Direction`]s with [`Path::directions`].
+///
+/// # Errors
+/// Returns [`PlanError::OutOfBounds`] or [`PlanError::Blocked`] if the
+/// robot could not stand on `start`.
+pub fn plan(world: &World, start: &Position) -> Result<Path, PlanError> {
+    if !world.in_bounds(start) {
+        return Err(PlanError::OutOfBounds(start.clone()));
+    }
+    if world.is_blocked(start) {
+        return Err(PlanError::Blocked(start.clone()));
+    }
+
+    // the robot itself does not block its way back to the start
+    let clear = |position: &Position| {
+        passable(world, position)
+            && (position == start || world.occupant(position).is_none())
+    };
+    // everything coverable the robot can get to, even across charge pads
+    let mut remaining = HashSet::new();
+    let mut seen = HashSet::from([start.clone()]);
+    let mut open = VecDeque::from([start.clone()]);
+    while let Some(cell) = open.pop_front() {
+        if coverable(world, &cell) {
+            remaining.insert(cell.clone());
+        }
+        for next in neighbours(&cell) {
+            if clear(&next) && seen.insert(next.clone()) {
+                open.push_back(next);
+            }
+        }
+    }
+
+    remaining.remove(start);
+    let mut cells = vec![start.clone()];
+    let mut current = start.clone();
+    while !remaining.is_empty() {
+        let route = match neighbours(&current).find(|next| remaining.contains(next)) {
+            Some(next) => vec![next],
+            None => nearest(clear, &current, &remaining).ok_or(PlanError::Unreachable)?,
+        };
+        for cell in route {
+            remaining.remove(&cell);
+            current = cell.clone();
+            cells.push(cell);
+        }
+    }
+    Ok(Path {
+        cost: (cells.len() - 1) as f64,
+        cells,
+    })
+}
+
+/// How well a route or a robot's visits cover the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageReport {
+    /// Coverable cells visited.
+    pub covered: usize,
+    /// Coverable cells of the whole world, reachable or not.
+    pub coverable: usize,
+    /// Distinct cells visited, coverable or not.
+    pub visited: usize,
+    /// Cells entered in total, each revisit counting again.
+    pub entered: usize,
+}
+
+impl CoverageReport {
+    /// Rates visiting `cells` in this order, the first one being the start.
+    pub fn new<'a>(world: &World, cells: impl IntoIterator<Item = &'a Position>) -> Self {
+        let mut visited = HashSet::new();
+        let mut entered = 0;
+        for cell in cells {
+            visited.insert(cell);
+            entered += 1;
+        }
+        Self::of(world, visited.into_iter(), entered)
+    }
+
+    fn of<'a>(world: &World, visited: impl Iterator<Item = &'a Position>, entered: usize) -> Self {
+        let mut report = Self {
+            covered: 0,
+            coverable: 0,
+            visited: 0,
+            entered,
+        };
+        for cell in visited {
+            report.visited += 1;
+            if coverable(world, cell) {
+                report.covered += 1;
+            }
+        }
+        report.coverable = (0..world.height() as i32)
+            .flat_map(|y| (0..world.width() as i32).map(move |x| Position::new(x, y)))
+            .filter(|position| coverable(world, position))
+            .count();
+        report
+    }
+
+    /// Share of the coverable cells visited, from `0.0` to `1.0`.
+    pub fn ratio(&self) -> f64 {
+        if self.coverable == 0 {
+            return 1.0;
+        }
+        self.covered as f64 / self.coverable as f64
+    }
+
+    /// Revisits per distinct visited cell, `0.0` for a route without revisits.
+    pub fn overhead(&self) -> f64 {
+        if self.visited == 0 {
+            return 0.0;
+        }
+        (self.entered - self.visited) as f64 / self.visited as f64
+    }
+}
+
+/// Cells a robot has entered, see [`crate::world::World::move_robot`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Visits {
+    cells: HashSet<Position>,
+    /// Cells entered in total, each revisit counting again.
+    entered: usize,
+}
+
+impl Visits {
+    /// Whether nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.entered == 0
+    }
+
+    /// Records that `cell` was entered.
+    pub fn record(&mut self, cell: Position) {
+        self.cells.insert(cell);
+        self.entered += 1;
+    }
+
+    /// Rates the visits so far.
+    pub fn report(&self, world: &World) -> CoverageReport {
+        CoverageReport::of(world, self.cells.iter(), self.entered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moveable::{Direction, MovementError};
+
+    /// 5x4 world with a wall in the middle column, open at the top,
+    /// and a charge pad in the bottom right corner.
+    fn world() -> World {
+        let mut world = World::new(4, 5);
+        for y in 0..3 {
+            world.add_tile(Position::new(2, y), Tile::Wall);
+        }
+        world.add_tile(Position::new(4, 0), Tile::ChargePad);
+        world
+    }
+
+    #[test]
+    fn plan_covers_every_reachable_cell() -> Result<(), PlanError> {
+        let world = world();
+        let path = plan(&world, &Position::new(0, 0))?;
+        let report = CoverageReport::new(&world, &path.cells);
+        assert_eq!(report.coverable, 16);
+        assert_eq!(report.covered, 16);
+        assert_eq!(report.ratio(), 1.0);
+        // every step goes to a neighbour and never through a wall
+        assert!(path.cells.windows(2).all(|pair| {
+            pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y) == 1
+                && !world.is_blocked(&pair[1])
+        }));
+        // the first column is swept up, the second one down
+        assert_eq!(
+            path.cells[..8],
+            [(0, 0), (0, 1), (0, 2), (0, 3), (1, 3), (1, 2), (1, 1), (1, 0)]
+                .map(|(x, y)| Position::new(x, y))
+        );
+        assert!(report.overhead() < 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn plan_skips_enclosed_cells() -> Result<(), PlanError> {
+        let mut world = world();
+        // closes off the right room
+        world.add_tile(Position::new(2, 3), Tile::Door {
+            open: true,
+            period: 0,
+        });
+        let path = plan(&world, &Position::new(1, 1))?;
+        let report = CoverageReport::new(&world, &path.cells);
+        assert_eq!((report.covered, report.coverable), (8, 15));
+        assert_eq!(report.ratio(), 8.0 / 15.0);
+        assert_eq!(
+            plan(&world, &Position::new(2, 0)),
+            Err(PlanError::Blocked(Position::new(2, 0)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn robots_track_their_visits() -> Result<(), MovementError> {
+        let mut world = world();
+        world.add_robot_new(String::from("rusty"));
+        world.move_robot("rusty", Direction::Forward { step: 2 })?;
+        world.move_robot("rusty", Direction::Backwards)?;
+        let report = world
+            .get_robot("rusty")
+            .map(|robot| robot.visits.report(&world));
+        // (0/0), (0/1), (0/2) and back to (0/1)
+        assert_eq!(
+            report.map(|report| (report.covered, report.entered)),
+            Some((3, 4))
+        );
+        assert_eq!(report.map(|report| report.overhead()), Some(1.0 / 3.0));
+        Ok(())
+    }
+}
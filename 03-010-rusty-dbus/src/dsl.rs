@@ -0,0 +1,193 @@
+//! A small language for robot programs.
+//!
+//! Programs consist of moves, loops, conditionals on sensor readings and
+//! named procedures:
+//!
+//! ```text
+//! # follow the wall on the left
+//! proc step {
+//!     if not wall_left { turn_left forward }
+//!     else if wall_ahead { turn_right }
+//!     else { forward }
+//! }
+//!
+//! repeat 20 { step }
+//! ```
+//!
+//! [`crate::dsl::parse`] turns the source into a [`crate::dsl::Script`],
+//! [`crate::dsl::run`] executes it on a robot of the world as a sequence of
+//! [`crate::moveable::Direction`]s. Errors of both carry the
+//! [`crate::dsl::Span`] of the offending source.
+//!
+//! # Statements
+//!
+//! - `forward [steps]`, moves up to 3 cells at once, 1 if omitted
+//! - `backwards`, `left`, `right`, `turn_left`, `turn_right` with an optional
+//!   repeat count
+//! - `repeat <count> { ... }`
+//! - `while <condition> { ... }`
+//! - `if <condition> { ... } else { ... }`, `else if` chains are allowed
+//! - `proc <name> { ... }` on the top level defines a procedure, `<name>` calls it
+//!
+//! Conditions are the sensors `wall_ahead`, `wall_left`, `wall_right`,
+//! `robot_ahead` and `on_charge_pad`, negated with `not`. A wall is anything
+//! blocking the cell, including the world border and closed doors.
+//! `#` starts a comment up to the end of the line.
+//! Blocks, `else if` chains and `not`s nest at most
+//! [`crate::dsl::MAX_NESTING`] levels deep.
+
+mod interpreter;
+mod parser;
+
+#[cfg(test)]
+mod tests;
+
+pub use interpreter::{MAX_DEPTH, MAX_STEPS, run};
+pub use parser::{MAX_NESTING, parse};
+
+use crate::moveable::{Direction, MovementError};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Location in the source, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// What a sensor condition asks about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    /// The cell in front of the robot is blocked or outside the world.
+    WallAhead,
+    /// The cell to the left of the robot is blocked or outside the world.
+    WallLeft,
+    /// The cell to the right of the robot is blocked or outside the world.
+    WallRight,
+    /// Another robot stands in front of the robot.
+    RobotAhead,
+    /// The robot stands on a charge pad.
+    OnChargePad,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Sensor(Sensor),
+    Not(Box<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    /// Executes `direction` `count` times.
+    Move { direction: Direction, count: u32 },
+    Repeat { count: u32, body: Vec<Statement> },
+    While { condition: Condition, body: Vec<Statement> },
+    If {
+        condition: Condition,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    /// Runs the procedure `name`.
+    Call { name: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub span: Span,
+    pub kind: StatementKind,
+}
+
+/// A parsed program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    /// Procedure bodies by name.
+    pub procedures: HashMap<String, Vec<Statement>>,
+    /// Top level statements, executed in order.
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// A character that starts no token.
+    UnexpectedCharacter(char),
+    /// The parser expected something else.
+    UnexpectedToken { expected: String, found: String },
+    /// A number does not fit.
+    InvalidNumber(String),
+    /// A call of a procedure that is not defined.
+    UnknownProcedure(String),
+    /// A second procedure with the same name.
+    DuplicateProcedure(String),
+    /// A move of the robot failed.
+    Movement(MovementError),
+    /// More than [`MAX_STEPS`] statements or loop iterations were executed.
+    StepLimit,
+    /// Procedures called each other deeper than [`MAX_DEPTH`].
+    RecursionLimit,
+    /// Blocks or conditions nested deeper than [`MAX_NESTING`].
+    NestingLimit,
+}
+
+/// A parse or runtime error and where in the source it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DslError {
+    pub span: Span,
+    pub kind: ErrorKind,
+}
+
+impl DslError {
+    fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    /// Formats the error with the offending line of `source` and a marker below it.
+    ///
+    /// `name` is shown as the origin, e.g. the file name.
+    pub fn report(&self, name: &str, source: &str) -> String {
+        let line = source.lines().nth(self.span.line.saturating_sub(1)).unwrap_or_default();
+        let number = self.span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let marker = " ".repeat(self.span.column.saturating_sub(1));
+        format!(
+            "error: {}\n{gutter}--> {name}:{}\n{gutter} |\n{number} | {line}\n{gutter} | {marker}^",
+            self.kind, self.span
+        )
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+            ErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            ErrorKind::InvalidNumber(number) => write!(f, "invalid number `{number}`"),
+            ErrorKind::UnknownProcedure(name) => write!(f, "unknown procedure `{name}`"),
+            ErrorKind::DuplicateProcedure(name) => {
+                write!(f, "procedure `{name}` is defined twice")
+            }
+            ErrorKind::Movement(error) => write!(f, "move failed: {error:?}"),
+            ErrorKind::StepLimit => write!(f, "more than {MAX_STEPS} steps, endless loop?"),
+            ErrorKind::RecursionLimit => {
+                write!(f, "procedures nested deeper than {MAX_DEPTH} calls")
+            }
+            ErrorKind::NestingLimit => {
+                write!(f, "blocks or conditions nested deeper than {MAX_NESTING} levels")
+            }
+        }
+    }
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
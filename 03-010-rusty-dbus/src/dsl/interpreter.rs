@@ -0,0 +1,148 @@
+//! Executes a [`Script`] on a robot of the [`World`].
+
+use super::{Condition, DslError, ErrorKind, Script, Sensor, Span, Statement, StatementKind};
+use crate::heading::Heading;
+use crate::moveable::MovementError;
+use crate::position::Position;
+use crate::world::{Tile, World};
+use log::debug;
+
+/// Statements and loop iterations a script may execute before it is stopped.
+pub const MAX_STEPS: usize = 10_000;
+
+/// How deep procedure calls may nest.
+pub const MAX_DEPTH: usize = 64;
+
+struct Interpreter<'a> {
+    script: &'a Script,
+    world: &'a mut World,
+    robot: &'a str,
+    steps: usize,
+    depth: usize,
+}
+
+impl Interpreter<'_> {
+    /// Counts one step at `span`, failing once [`MAX_STEPS`] is exceeded.
+    fn count_step(&mut self, span: Span) -> Result<(), DslError> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(DslError::new(span, ErrorKind::StepLimit));
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<(), DslError> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), DslError> {
+        let span = statement.span;
+        self.count_step(span)?;
+        match &statement.kind {
+            StatementKind::Move { direction, count } => {
+                for _ in 0..*count {
+                    self.count_step(span)?;
+                    debug!("{}: {direction:?} at {span}", self.robot);
+                    self.world
+                        .move_robot(self.robot, direction.clone())
+                        .map_err(|e| DslError::new(span, ErrorKind::Movement(e)))?;
+                }
+            }
+            StatementKind::Repeat { count, body } => {
+                for _ in 0..*count {
+                    self.count_step(span)?;
+                    self.block(body)?;
+                }
+            }
+            StatementKind::While { condition, body } => {
+                while self.check(condition, span)? {
+                    self.count_step(span)?;
+                    self.block(body)?;
+                }
+            }
+            StatementKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if self.check(condition, span)? {
+                    self.block(then)?;
+                } else {
+                    self.block(otherwise)?;
+                }
+            }
+            StatementKind::Call { name } => {
+                let script = self.script;
+                let body = script.procedures.get(name).ok_or_else(|| {
+                    DslError::new(span, ErrorKind::UnknownProcedure(name.clone()))
+                })?;
+                if self.depth >= MAX_DEPTH {
+                    return Err(DslError::new(span, ErrorKind::RecursionLimit));
+                }
+                self.depth += 1;
+                let result = self.block(body);
+                self.depth -= 1;
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, condition: &Condition, span: Span) -> Result<bool, DslError> {
+        match condition {
+            Condition::Not(condition) => Ok(!self.check(condition, span)?),
+            Condition::Sensor(sensor) => self.sense(*sensor, span),
+        }
+    }
+
+    fn sense(&self, sensor: Sensor, span: Span) -> Result<bool, DslError> {
+        let robot = self.world.get_robot(self.robot).ok_or_else(|| {
+            DslError::new(
+                span,
+                ErrorKind::Movement(MovementError::UnknownRobot(self.robot.to_string())),
+            )
+        })?;
+        let next_to = |heading: Heading| {
+            let (dx, dy) = heading.delta();
+            Position::new(robot.position.x + dx, robot.position.y + dy)
+        };
+        let wall = |position: Position| {
+            !self.world.in_bounds(&position) || self.world.is_blocked(&position)
+        };
+        Ok(match sensor {
+            Sensor::WallAhead => wall(next_to(robot.heading)),
+            Sensor::WallLeft => wall(next_to(robot.heading.turn_left())),
+            Sensor::WallRight => wall(next_to(robot.heading.turn_right())),
+            Sensor::RobotAhead => self
+                .world
+                .occupant(&next_to(robot.heading))
+                .is_some_and(|other| other.name != robot.name),
+            Sensor::OnChargePad => {
+                matches!(self.world.tile(&robot.position), Some(Tile::ChargePad))
+            }
+        })
+    }
+}
+
+/// Runs `script` for robot `robot`, moving it directly in `world`.
+///
+/// Returns the number of executed steps.
+///
+/// # Errors
+/// Stops at the first failing move, after [`MAX_STEPS`] steps or when
+/// procedures nest deeper than [`MAX_DEPTH`], and reports where in the
+/// script that happened. Moves done before stay done.
+pub fn run(script: &Script, world: &mut World, robot: &str) -> Result<usize, DslError> {
+    let mut interpreter = Interpreter {
+        script,
+        world,
+        robot,
+        steps: 0,
+        depth: 0,
+    };
+    interpreter.block(&script.body)?;
+    Ok(interpreter.steps)
+}
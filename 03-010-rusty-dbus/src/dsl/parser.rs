@@ -0,0 +1,361 @@
+//! Tokenizer and recursive descent parser of the robot language.
+
+use super::{Condition, DslError, ErrorKind, Script, Sensor, Span, Statement, StatementKind};
+use crate::moveable::Direction;
+
+/// How deep blocks, `else if` chains and `not`s may nest.
+pub const MAX_NESTING: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(u32),
+    Open,
+    Close,
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("`{word}`"),
+            Token::Number(number) => format!("`{number}`"),
+            Token::Open => "`{`".to_string(),
+            Token::Close => "`}`".to_string(),
+            Token::End => "end of file".to_string(),
+        }
+    }
+}
+
+/// Splits `source` into tokens, the last one is always [`Token::End`].
+fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, DslError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut span = Span { line: 1, column: 1 };
+    let advance = |span: &mut Span, c: char| {
+        if c == '\n' {
+            span.line += 1;
+            span.column = 1;
+        } else {
+            span.column += 1;
+        }
+    };
+
+    while let Some(&c) = chars.peek() {
+        let start = span;
+        if c == '#' {
+            while let Some(c) = chars.next_if(|&c| c != '\n') {
+                advance(&mut span, c);
+            }
+        } else if c.is_whitespace() {
+            chars.next();
+            advance(&mut span, c);
+        } else if c == '{' || c == '}' {
+            chars.next();
+            advance(&mut span, c);
+            let token = if c == '{' { Token::Open } else { Token::Close };
+            tokens.push((token, start));
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_alphanumeric) {
+                advance(&mut span, c);
+                digits.push(c);
+            }
+            let number = digits
+                .parse()
+                .map_err(|_| DslError::new(start, ErrorKind::InvalidNumber(digits)))?;
+            tokens.push((Token::Number(number), start));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                advance(&mut span, c);
+                word.push(c);
+            }
+            tokens.push((Token::Word(word), start));
+        } else {
+            return Err(DslError::new(start, ErrorKind::UnexpectedCharacter(c)));
+        }
+    }
+    tokens.push((Token::End, span));
+    Ok(tokens)
+}
+
+/// Words that cannot name a procedure.
+const KEYWORDS: &[&str] = &["proc", "repeat", "while", "if", "else", "not"];
+
+fn direction(word: &str) -> Option<Direction> {
+    match word {
+        "forward" => Some(Direction::Forward { step: 1 }),
+        "backwards" => Some(Direction::Backwards),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        "turn_left" => Some(Direction::TurnLeft),
+        "turn_right" => Some(Direction::TurnRight),
+        _ => None,
+    }
+}
+
+fn sensor(word: &str) -> Option<Sensor> {
+    match word {
+        "wall_ahead" => Some(Sensor::WallAhead),
+        "wall_left" => Some(Sensor::WallLeft),
+        "wall_right" => Some(Sensor::WallRight),
+        "robot_ahead" => Some(Sensor::RobotAhead),
+        "on_charge_pad" => Some(Sensor::OnChargePad),
+        _ => None,
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    KEYWORDS.contains(&word) || direction(word).is_some() || sensor(word).is_some()
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(Token, Span) {
+        // tokenize always ends with `End`, which is never consumed
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> (Token, Span) {
+        let token = self.peek().clone();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self, expected: &str) -> DslError {
+        let (token, span) = self.peek();
+        DslError::new(
+            *span,
+            ErrorKind::UnexpectedToken {
+                expected: expected.to_string(),
+                found: token.describe(),
+            },
+        )
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<Span, DslError> {
+        if self.peek().0 != expected {
+            return Err(self.unexpected(&expected.describe()));
+        }
+        Ok(self.next().1)
+    }
+
+    /// Runs `parse` one level deeper, failing at the next token beyond [`MAX_NESTING`].
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, DslError>,
+    ) -> Result<T, DslError> {
+        if self.depth >= MAX_NESTING {
+            return Err(DslError::new(self.peek().1, ErrorKind::NestingLimit));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn number(&mut self) -> Result<u32, DslError> {
+        match self.peek().0 {
+            Token::Number(number) => {
+                self.next();
+                Ok(number)
+            }
+            _ => Err(self.unexpected("a number")),
+        }
+    }
+
+    fn script(&mut self) -> Result<Script, DslError> {
+        let mut script = Script::default();
+        loop {
+            match &self.peek().0 {
+                Token::End => return Ok(script),
+                Token::Word(word) if word == "proc" => {
+                    self.next();
+                    let (name, span) = match self.next() {
+                        (Token::Word(name), span) if !is_reserved(&name) => (name, span),
+                        (token, span) => {
+                            return Err(DslError::new(
+                                span,
+                                ErrorKind::UnexpectedToken {
+                                    expected: "a procedure name".to_string(),
+                                    found: token.describe(),
+                                },
+                            ));
+                        }
+                    };
+                    let body = self.block()?;
+                    if script.procedures.insert(name.clone(), body).is_some() {
+                        return Err(DslError::new(span, ErrorKind::DuplicateProcedure(name)));
+                    }
+                }
+                _ => script.body.push(self.statement()?),
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, DslError> {
+        self.nested(|parser| {
+            parser.expect(Token::Open)?;
+            let mut statements = Vec::new();
+            while parser.peek().0 != Token::Close {
+                statements.push(parser.statement()?);
+            }
+            parser.next();
+            Ok(statements)
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement, DslError> {
+        let (token, span) = self.peek().clone();
+        let Token::Word(word) = token else {
+            return Err(self.unexpected("a statement"));
+        };
+        self.next();
+        let kind = if let Some(direction) = direction(&word) {
+            let count = match self.peek().0 {
+                Token::Number(_) => self.number()?,
+                _ => 1,
+            };
+            match direction {
+                Direction::Forward { .. } => StatementKind::Move {
+                    direction: Direction::Forward {
+                        step: i32::try_from(count).unwrap_or(i32::MAX),
+                    },
+                    count: 1,
+                },
+                direction => StatementKind::Move { direction, count },
+            }
+        } else {
+            match word.as_str() {
+                "repeat" => {
+                    let count = self.number()?;
+                    StatementKind::Repeat {
+                        count,
+                        body: self.block()?,
+                    }
+                }
+                "while" => StatementKind::While {
+                    condition: self.condition()?,
+                    body: self.block()?,
+                },
+                "if" => self.conditional()?,
+                _ if is_reserved(&word) => {
+                    return Err(DslError::new(
+                        span,
+                        ErrorKind::UnexpectedToken {
+                            expected: "a statement".to_string(),
+                            found: format!("`{word}`"),
+                        },
+                    ));
+                }
+                _ => StatementKind::Call { name: word },
+            }
+        };
+        Ok(Statement { span, kind })
+    }
+
+    /// Parses the rest of an `if` after the keyword, including `else if` chains.
+    fn conditional(&mut self) -> Result<StatementKind, DslError> {
+        let condition = self.condition()?;
+        let then = self.block()?;
+        let otherwise = match &self.peek().0 {
+            Token::Word(word) if word == "else" => {
+                self.next();
+                match &self.peek() {
+                    (Token::Word(word), span) if word == "if" => {
+                        let span = *span;
+                        self.nested(|parser| {
+                            parser.next();
+                            Ok(vec![Statement {
+                                span,
+                                kind: parser.conditional()?,
+                            }])
+                        })?
+                    }
+                    _ => self.block()?,
+                }
+            }
+            _ => Vec::new(),
+        };
+        Ok(StatementKind::If {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+
+    fn condition(&mut self) -> Result<Condition, DslError> {
+        match &self.peek().0 {
+            Token::Word(word) if word == "not" => self.nested(|parser| {
+                parser.next();
+                Ok(Condition::Not(Box::new(parser.condition()?)))
+            }),
+            Token::Word(word) => match sensor(word) {
+                Some(sensor) => {
+                    self.next();
+                    Ok(Condition::Sensor(sensor))
+                }
+                None => Err(self.unexpected("a sensor")),
+            },
+            _ => Err(self.unexpected("a sensor")),
+        }
+    }
+}
+
+/// Reports the first call of a procedure that `script` does not define.
+fn check_calls(script: &Script, statements: &[Statement]) -> Result<(), DslError> {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Move { .. } => {}
+            StatementKind::Repeat { body, .. } | StatementKind::While { body, .. } => {
+                check_calls(script, body)?;
+            }
+            StatementKind::If {
+                then, otherwise, ..
+            } => {
+                check_calls(script, then)?;
+                check_calls(script, otherwise)?;
+            }
+            StatementKind::Call { name } => {
+                if !script.procedures.contains_key(name) {
+                    return Err(DslError::new(
+                        statement.span,
+                        ErrorKind::UnknownProcedure(name.clone()),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a robot program.
+///
+/// # Errors
+/// Returns the first syntax error, a call of an undefined procedure, a
+/// procedure defined twice or nesting deeper than [`MAX_NESTING`], with its
+/// location.
+pub fn parse(source: &str) -> Result<Script, DslError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        depth: 0,
+    };
+    let script = parser.script()?;
+    // in source order, so the first unknown call is reported
+    let mut bodies: Vec<&Vec<Statement>> = script.procedures.values().collect();
+    bodies.sort_by_key(|body| body.first().map(|statement| statement.span));
+    bodies.insert(0, &script.body);
+    for body in bodies {
+        check_calls(&script, body)?;
+    }
+    Ok(script)
+}
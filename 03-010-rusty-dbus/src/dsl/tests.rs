@@ -0,0 +1,209 @@
+use super::*;
+use crate::heading::Heading;
+use crate::position::Position;
+use crate::world::{Tile, World};
+
+fn world() -> World {
+    let mut world = World::new(5, 5);
+    world.add_robot_new(String::from("rusty"));
+    world
+}
+
+fn run_source(world: &mut World, source: &str) -> Result<usize, DslError> {
+    run(&parse(source)?, world, "rusty")
+}
+
+#[test]
+fn parse_statements_with_spans() -> Result<(), DslError> {
+    let script = parse("forward 2\n  repeat 3 { turn_left }\nproc spin { turn_right 4 }")?;
+    assert_eq!(script.body.len(), 2);
+    assert_eq!(
+        script.body[0].kind,
+        StatementKind::Move {
+            direction: Direction::Forward { step: 2 },
+            count: 1,
+        }
+    );
+    assert_eq!(script.body[1].span, Span { line: 2, column: 3 });
+    assert_eq!(
+        script.procedures.get("spin"),
+        Some(&vec![Statement {
+            span: Span {
+                line: 3,
+                column: 13
+            },
+            kind: StatementKind::Move {
+                direction: Direction::TurnRight,
+                count: 4,
+            },
+        }])
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_errors_point_at_the_source() {
+    let error = parse("repeat 2 {\n  forward\n  jump 3\n}").err();
+    assert_eq!(
+        error,
+        Some(DslError {
+            span: Span { line: 3, column: 8 },
+            kind: ErrorKind::UnexpectedToken {
+                expected: "a statement".to_string(),
+                found: "`3`".to_string(),
+            },
+        })
+    );
+    let error = parse("forward\nif wall_ahead { dance }").err();
+    assert_eq!(
+        error.map(|error| (error.span, error.kind)),
+        Some((
+            Span {
+                line: 2,
+                column: 17
+            },
+            ErrorKind::UnknownProcedure("dance".to_string())
+        ))
+    );
+    assert!(matches!(
+        parse("forward; turn_left").map_err(|error| error.kind),
+        Err(ErrorKind::UnexpectedCharacter(';'))
+    ));
+    assert!(matches!(
+        parse("while forward { }").map_err(|error| error.kind),
+        Err(ErrorKind::UnexpectedToken { .. })
+    ));
+}
+
+#[test]
+fn parse_limits_nesting() {
+    let source = format!("if {}wall_ahead {{ }}", "not ".repeat(200_000));
+    assert_eq!(
+        parse(&source).err(),
+        Some(DslError {
+            span: Span {
+                line: 1,
+                column: 4 + 4 * MAX_NESTING
+            },
+            kind: ErrorKind::NestingLimit,
+        })
+    );
+    let source = "repeat 1 { ".repeat(100_000);
+    assert!(matches!(
+        parse(&source).map_err(|error| error.kind),
+        Err(ErrorKind::NestingLimit)
+    ));
+    let source = format!(
+        "if wall_ahead {{ }}{}",
+        " else if wall_ahead { }".repeat(100)
+    );
+    assert!(matches!(
+        parse(&source).map_err(|error| error.kind),
+        Err(ErrorKind::NestingLimit)
+    ));
+}
+
+#[test]
+fn deepest_nesting_runs() {
+    // every procedure calls the next from the innermost block allowed
+    let nested = |body: String| {
+        format!(
+            "{}if not robot_ahead {{ {body} }}{}",
+            "repeat 1 { ".repeat(MAX_NESTING - 2),
+            " }".repeat(MAX_NESTING - 2)
+        )
+    };
+    let mut source = (0..MAX_DEPTH)
+        .map(|depth| {
+            format!(
+                "proc p{depth} {{ {} }}\n",
+                nested(format!("p{}", depth + 1))
+            )
+        })
+        .collect::<String>();
+    source.push_str(&format!("proc p{MAX_DEPTH} {{ turn_left }}\n"));
+    source.push_str(&nested(String::from("p1")));
+    // programs run on the main thread, whose stack is 8 MiB
+    let result = std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(move || {
+            let mut world = world();
+            run_source(&mut world, &source)
+                .map(|_| world.get_robot("rusty").map(|robot| robot.heading))
+        })
+        .map(|thread| thread.join());
+    assert!(matches!(
+        result,
+        Ok(Ok(Ok(Some(heading)))) if heading == Heading::default().turn_left()
+    ));
+}
+
+#[test]
+fn report_marks_the_column() {
+    let error = DslError {
+        span: Span { line: 2, column: 3 },
+        kind: ErrorKind::UnknownProcedure("dance".to_string()),
+    };
+    assert_eq!(
+        error.report("moves.rusty", "forward\n  dance\n"),
+        "error: unknown procedure `dance`\n --> moves.rusty:2:3\n  |\n2 |   dance\n  |   ^"
+    );
+}
+
+#[test]
+fn run_follows_conditions_and_procedures() -> Result<(), DslError> {
+    let mut world = world();
+    let source = "
+        proc to_wall { while not wall_ahead { forward } }
+        to_wall
+        if wall_right { turn_left } else { turn_right }
+        to_wall
+    ";
+    run_source(&mut world, source)?;
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(4, 4)));
+    assert_eq!(
+        world.get_robot("rusty").map(|robot| robot.heading),
+        Some(Heading::East)
+    );
+    Ok(())
+}
+
+#[test]
+fn run_reports_failed_moves_with_span() {
+    let mut world = world();
+    world.add_tile(Position::new(0, 2), Tile::Wall);
+    let result = run_source(&mut world, "forward\n# into the wall\nforward 2");
+    assert_eq!(
+        result,
+        Err(DslError {
+            span: Span { line: 3, column: 1 },
+            kind: ErrorKind::Movement(MovementError::Blocked {
+                at: Position::new(0, 2)
+            }),
+        })
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 1)));
+}
+
+#[test]
+fn run_stops_endless_programs() {
+    let mut world = world();
+    let result = run_source(&mut world, "while not robot_ahead { turn_left }");
+    assert_eq!(
+        result.map_err(|error| error.kind),
+        Err(ErrorKind::StepLimit)
+    );
+    let result = run_source(&mut world, "turn_right\nturn_left 4000000000");
+    assert_eq!(
+        result,
+        Err(DslError {
+            span: Span { line: 2, column: 1 },
+            kind: ErrorKind::StepLimit,
+        })
+    );
+    let result = run_source(&mut world, "proc again { again }\nagain");
+    assert_eq!(
+        result.map_err(|error| error.kind),
+        Err(ErrorKind::RecursionLimit)
+    );
+}
@@ -0,0 +1,300 @@
+//! Frontier-based autonomous exploration.
+//!
+//! A robot with an [`crate::explore::Exploration`] drives on its own, one
+//! cell per tick, to the nearest frontier of its belief map (see
+//! [`crate::mapping`]): a cell it believes free next to a cell it knows
+//! nothing about. Routes only lead over cells believed free, so the robot
+//! never plans through what it has not seen yet.
+//!
+//! Once the battery would barely last back to the nearest charge pad the
+//! robot has seen, it returns there and charges until full before going on.
+//! Exploration ends when no frontier is reachable anymore.
+
+use crate::command::Command;
+use crate::mapping::Belief;
+use crate::planner::Path;
+use crate::position::Position;
+use crate::robot::{ENERGY_PER_STEP, Robot};
+use crate::world::{Tile, World};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Steps of battery kept on top of the way back to a charge pad.
+pub const RESERVE: u32 = 5;
+
+/// State of a robot exploring on its own.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Exploration {
+    /// Share of the map known when last reported, in percent.
+    #[serde(default)]
+    pub coverage: f64,
+    /// Heading back to a charge pad.
+    #[serde(default)]
+    pub returning: bool,
+    /// Frontiers the robot reached without learning anything about the
+    /// unknown cell next to them, e.g. because no beam of its lidar points
+    /// there. They are not visited again.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub abandoned: HashSet<Position>,
+}
+
+/// What an exploring robot does next.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// Queue this command.
+    Command(Command),
+    /// Do nothing this tick, e.g. before the first scan.
+    Wait,
+    /// Nothing is left to explore, or the robot cannot move anymore.
+    Finished,
+}
+
+/// Cells reachable over believed-free cells, nearest first.
+struct Search {
+    order: Vec<Position>,
+    came_from: HashMap<Position, Position>,
+}
+
+impl Search {
+    /// Breadth-first search from `robot` over the cells it believes free
+    /// and does not see another robot on.
+    fn new(world: &World, robot: &Robot, passable: impl Fn(&Position) -> bool) -> Self {
+        let mut order = vec![robot.position.clone()];
+        let mut came_from = HashMap::new();
+        let mut open = VecDeque::from([robot.position.clone()]);
+        while let Some(cell) = open.pop_front() {
+            for next in neighbours(&cell) {
+                if next == robot.position || came_from.contains_key(&next) || !passable(&next) {
+                    continue;
+                }
+                if world.occupant(&next).is_some() {
+                    continue;
+                }
+                came_from.insert(next.clone(), cell.clone());
+                order.push(next.clone());
+                open.push_back(next);
+            }
+        }
+        Self { order, came_from }
+    }
+
+    /// Number of steps from the robot to `cell`.
+    fn distance(&self, cell: &Position) -> u32 {
+        let mut distance = 0;
+        let mut cell = cell;
+        while let Some(previous) = self.came_from.get(cell) {
+            distance += 1;
+            cell = previous;
+        }
+        distance
+    }
+
+    /// The first cell on the way from the robot to `goal`.
+    fn first_step(&self, goal: &Position) -> Option<Position> {
+        let mut cell = goal;
+        loop {
+            let previous = self.came_from.get(cell)?;
+            if !self.came_from.contains_key(previous) {
+                return Some(cell.clone());
+            }
+            cell = previous;
+        }
+    }
+}
+
+fn neighbours(cell: &Position) -> [Position; 4] {
+    [(0, 1), (1, 0), (0, -1), (-1, 0)].map(|(dx, dy)| Position::new(cell.x + dx, cell.y + dy))
+}
+
+impl Exploration {
+    /// Decides what `robot` does next, based on its belief map.
+    ///
+    /// Meant for robots without pending commands.
+    pub fn decide(&mut self, world: &World, robot: &Robot) -> Decision {
+        let Some(belief) = &robot.belief else {
+            return Decision::Wait;
+        };
+        let on_pad = matches!(world.tile(&robot.position), Some(Tile::ChargePad));
+        if self.returning && on_pad {
+            self.returning = false;
+            return Decision::Command(Command::ChargeUntilFull);
+        }
+        if robot.state_of_charge < ENERGY_PER_STEP {
+            return if on_pad {
+                Decision::Command(Command::ChargeUntilFull)
+            } else {
+                Decision::Finished
+            };
+        }
+
+        let free = |cell: &Position| belief.belief(cell) == Belief::Free;
+        let frontier = |cell: &Position| {
+            free(cell)
+                && neighbours(cell).iter().any(|next| {
+                    world.in_bounds(next) && belief.belief(next) == Belief::Unknown
+                })
+        };
+        let search = Search::new(world, robot, free);
+
+        let pad = search
+            .order
+            .iter()
+            .find(|cell| matches!(world.tile(cell), Some(Tile::ChargePad)));
+        if let Some(pad) = pad {
+            let battery = u32::from(robot.state_of_charge / ENERGY_PER_STEP.max(1));
+            if self.returning || battery <= search.distance(pad) + RESERVE {
+                if *pad == robot.position {
+                    return Decision::Command(Command::ChargeUntilFull);
+                }
+                self.returning = true;
+                return self.step_towards(&search, robot, pad);
+            }
+        }
+
+        if frontier(&robot.position) {
+            // standing on it did not reveal its unknown neighbour
+            self.abandoned.insert(robot.position.clone());
+        }
+        match search
+            .order
+            .iter()
+            .find(|cell| frontier(cell) && !self.abandoned.contains(cell))
+        {
+            Some(goal) => self.step_towards(&search, robot, goal),
+            None => Decision::Finished,
+        }
+    }
+
+    fn step_towards(&self, search: &Search, robot: &Robot, goal: &Position) -> Decision {
+        let Some(next) = search.first_step(goal) else {
+            return Decision::Wait;
+        };
+        let path = Path {
+            cells: vec![robot.position.clone(), next],
+            cost: 1.0,
+        };
+        match path.directions(robot.heading).into_iter().next() {
+            Some(direction) => Decision::Command(direction.into()),
+            None => Decision::Wait,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::OccupancyGrid;
+    use crate::moveable::MovementError;
+    use crate::sensor::Lidar;
+    use crate::simulation::WorldEvent;
+
+    /// 8x6 world with a wall splitting it into two rooms joined at the top.
+    fn world() -> World {
+        let mut world = World::new(6, 8);
+        for y in 0..4 {
+            world.add_tile(Position::new(4, y), Tile::Wall);
+        }
+        world.add_robot_new(String::from("rusty"));
+        world
+    }
+
+    /// Steps `world` until exploration of `rusty` ends, at most `ticks` times.
+    fn explore(world: &mut World, ticks: u64) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        for tick in 1..=ticks {
+            events.extend(world.step(tick));
+            if world
+                .get_robot("rusty")
+                .is_some_and(|robot| robot.exploration.is_none())
+            {
+                break;
+            }
+        }
+        events
+    }
+
+    fn coverage(world: &World) -> f64 {
+        world
+            .get_robot("rusty")
+            .and_then(|robot| robot.belief.as_ref())
+            .map_or(0.0, OccupancyGrid::coverage)
+    }
+
+    #[test]
+    fn explores_both_rooms() -> Result<(), MovementError> {
+        let mut world = world();
+        world.start_exploration("rusty")?;
+        let events = explore(&mut world, 200);
+
+        assert_eq!(coverage(&world), 100.0);
+        assert!(events.contains(&WorldEvent::ExplorationFinished {
+            name: String::from("rusty"),
+            coverage: 100.0,
+        }));
+        let reports: Vec<f64> = events
+            .iter()
+            .filter_map(|event| match event {
+                WorldEvent::ExplorationProgress { coverage, .. } => Some(*coverage),
+                _ => None,
+            })
+            .collect();
+        assert!(reports.len() > 1);
+        assert!(reports.is_sorted());
+        // the room behind the wall was entered
+        assert!(
+            world
+                .get_robot_position("rusty")
+                .is_some_and(|position| position.x > 4 || position.y > 3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn returns_to_the_charge_pad_when_low() -> Result<(), MovementError> {
+        let mut world = world();
+        world.add_tile(Position::new(0, 0), Tile::ChargePad);
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            robot.state_of_charge = 10;
+        }
+        world.start_exploration("rusty")?;
+        let events = explore(&mut world, 1000);
+
+        assert_eq!(coverage(&world), 100.0);
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, WorldEvent::RobotCharged { .. }))
+        );
+        assert!(!events.iter().any(|event| matches!(
+            event,
+            WorldEvent::CommandFailed {
+                error: MovementError::BatteryEmpty,
+                ..
+            }
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn gives_up_frontiers_it_cannot_see() -> Result<(), MovementError> {
+        let mut world = world();
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            // a single beam straight ahead never sees the sides
+            robot.lidar = Lidar {
+                beams: 1,
+                range: 3.0,
+            };
+        }
+        world.start_exploration("rusty")?;
+        explore(&mut world, 200);
+
+        assert!(
+            world
+                .get_robot("rusty")
+                .is_some_and(|robot| robot.exploration.is_none())
+        );
+        assert!(coverage(&world) < 100.0);
+        assert!(world.start_exploration("karl").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+//! Procedural world generation.
+//!
+//! [`crate::generate::generate`] builds a [`crate::world::World`] of the
+//! chosen [`crate::generate::Layout`] from a seed; the same seed always
+//! gives the same world.
+//!
+//! - [`Layout::Maze`]: a perfect maze carved by a recursive backtracker,
+//!   passages and walls are one cell wide.
+//! - [`Layout::Rooms`]: rooms in the leaves of a binary space partition,
+//!   sibling rooms joined by L-shaped corridors.
+//! - [`Layout::Caves`]: random noise smoothed by a cellular automaton.
+//!
+//! Whatever the layout, free cells that cannot be reached from the largest
+//! open area are walled up afterwards, so every free cell is reachable
+//! from every other. [`crate::generate::scatter_charge_pads`] then puts
+//! charge pads on random free cells.
+
+use crate::position::Position;
+use crate::world::{Tile, World};
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Share of walls in the initial noise of [`Layout::Caves`].
+const CAVE_FILL: f64 = 0.45;
+
+/// Smoothing rounds of [`Layout::Caves`].
+const CAVE_ROUNDS: usize = 5;
+
+/// Smallest side of a [`Layout::Rooms`] partition, walls included.
+const MIN_LEAF: i32 = 6;
+
+/// Kinds of worlds the generator builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// Recursive-backtracker maze.
+    Maze,
+    /// Rooms and corridors from a binary space partition.
+    Rooms,
+    /// Cellular-automata caves.
+    Caves,
+}
+
+/// Walls of a world under construction, row by row.
+struct Grid {
+    width: i32,
+    height: i32,
+    walls: Vec<bool>,
+}
+
+impl Grid {
+    fn new(width: u32, height: u32, wall: bool) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            walls: vec![wall; width as usize * height as usize],
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width && y < self.height)
+            .then(|| y as usize * self.width as usize + x as usize)
+    }
+
+    /// Whether `(x/y)` is a wall, the outside counts as wall.
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_none_or(|index| self.walls[index])
+    }
+
+    fn set(&mut self, x: i32, y: i32, wall: bool) {
+        if let Some(index) = self.index(x, y) {
+            self.walls[index] = wall;
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (i32, i32)> + use<> {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    /// Walls up every free cell outside the largest connected free area.
+    fn keep_largest_area(&mut self) {
+        let mut area = vec![usize::MAX; self.walls.len()];
+        let mut sizes = Vec::new();
+        for (x, y) in self.cells() {
+            let Some(start) = self.index(x, y) else {
+                continue;
+            };
+            if self.walls[start] || area[start] != usize::MAX {
+                continue;
+            }
+            let id = sizes.len();
+            let mut size = 0;
+            area[start] = id;
+            let mut open = VecDeque::from([(x, y)]);
+            while let Some((x, y)) = open.pop_front() {
+                size += 1;
+                for (dx, dy) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                    if let Some(next) = self.index(x + dx, y + dy)
+                        && !self.walls[next]
+                        && area[next] == usize::MAX
+                    {
+                        area[next] = id;
+                        open.push_back((x + dx, y + dy));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        let largest = (0..sizes.len()).max_by_key(|&id| sizes[id]);
+        for (index, wall) in self.walls.iter_mut().enumerate() {
+            if !*wall && Some(area[index]) != largest {
+                *wall = true;
+            }
+        }
+    }
+
+    fn into_world(self) -> World {
+        let mut world = World::new(self.height as u32, self.width as u32);
+        for (x, y) in self.cells() {
+            if self.is_wall(x, y) {
+                world.add_tile(Position::new(x, y), Tile::Wall);
+            }
+        }
+        world
+    }
+}
+
+/// Carves passages on even cells, knocking out the odd cells between them.
+fn maze(grid: &mut Grid, rng: &mut StdRng) {
+    grid.set(0, 0, false);
+    let mut stack = vec![(0, 0)];
+    while let Some(&(x, y)) = stack.last() {
+        let unvisited: Vec<(i32, i32)> = [(0, 2), (2, 0), (0, -2), (-2, 0)]
+            .into_iter()
+            .filter(|(dx, dy)| grid.index(x + dx, y + dy).is_some() && grid.is_wall(x + dx, y + dy))
+            .collect();
+        match unvisited.choose(rng) {
+            Some(&(dx, dy)) => {
+                grid.set(x + dx / 2, y + dy / 2, false);
+                grid.set(x + dx, y + dy, false);
+                stack.push((x + dx, y + dy));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+/// A rectangle of cells, `x`/`y` is its lower left corner.
+#[derive(Clone, Copy)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+/// Splits `area` until it gets too small, carves a room into every leaf and
+/// joins the rooms of both halves. Returns a cell inside the carved rooms.
+fn rooms(grid: &mut Grid, rng: &mut StdRng, area: Area) -> (i32, i32) {
+    let split_x = area.width >= 2 * MIN_LEAF;
+    let split_y = area.height >= 2 * MIN_LEAF;
+    let vertical = match (split_x, split_y) {
+        (false, false) => {
+            // the room keeps a wall to the leaf's border on every side
+            let width = rng.random_range(2..=area.width - 2);
+            let height = rng.random_range(2..=area.height - 2);
+            let x = area.x + rng.random_range(1..=area.width - 1 - width);
+            let y = area.y + rng.random_range(1..=area.height - 1 - height);
+            for (dx, dy) in (0..height).flat_map(|dy| (0..width).map(move |dx| (dx, dy))) {
+                grid.set(x + dx, y + dy, false);
+            }
+            return (x + width / 2, y + height / 2);
+        }
+        (true, true) => area.width >= area.height,
+        (vertical, _) => vertical,
+    };
+    let (first, second) = if vertical {
+        let split = rng.random_range(MIN_LEAF..=area.width - MIN_LEAF);
+        (
+            Area {
+                width: split,
+                ..area
+            },
+            Area {
+                x: area.x + split,
+                width: area.width - split,
+                ..area
+            },
+        )
+    } else {
+        let split = rng.random_range(MIN_LEAF..=area.height - MIN_LEAF);
+        (
+            Area {
+                height: split,
+                ..area
+            },
+            Area {
+                y: area.y + split,
+                height: area.height - split,
+                ..area
+            },
+        )
+    };
+    let a = rooms(grid, rng, first);
+    let b = rooms(grid, rng, second);
+    // L-shaped corridor, horizontal or vertical leg first
+    let corner = if rng.random_bool(0.5) {
+        (b.0, a.1)
+    } else {
+        (a.0, b.1)
+    };
+    for (from, to) in [(a, corner), (corner, b)] {
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            for y in from.1.min(to.1)..=from.1.max(to.1) {
+                grid.set(x, y, false);
+            }
+        }
+    }
+    a
+}
+
+/// Fills the grid with noise and lets the walls clump together.
+fn caves(grid: &mut Grid, rng: &mut StdRng) {
+    for wall in &mut grid.walls {
+        *wall = rng.random_bool(CAVE_FILL);
+    }
+    for _ in 0..CAVE_ROUNDS {
+        let walls = grid
+            .cells()
+            .map(|(x, y)| {
+                let around = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| (dx, dy) != (0, 0) && grid.is_wall(x + dx, y + dy))
+                    .count();
+                around >= 5 || (around == 4 && grid.is_wall(x, y))
+            })
+            .collect();
+        grid.walls = walls;
+    }
+}
+
+/// Builds a `width` x `height` world of the given `layout` from `seed`.
+///
+/// Every free cell is reachable from every other one. Worlds too small for
+/// a layout come out as they can; unless `width` or `height` is 0, at least
+/// one cell is free.
+pub fn generate(layout: Layout, width: u32, height: u32, seed: u64) -> World {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = Grid::new(width, height, true);
+    match layout {
+        Layout::Maze => maze(&mut grid, &mut rng),
+        Layout::Rooms if grid.width >= 4 && grid.height >= 4 => {
+            let area = Area {
+                x: 0,
+                y: 0,
+                width: grid.width,
+                height: grid.height,
+            };
+            rooms(&mut grid, &mut rng, area);
+        }
+        Layout::Rooms => grid.set(0, 0, false),
+        Layout::Caves => caves(&mut grid, &mut rng),
+    }
+    grid.keep_largest_area();
+    if grid.walls.iter().all(|&wall| wall) {
+        grid.set(0, 0, false);
+    }
+    grid.into_world()
+}
+
+/// Cells without a tile, row by row from `(0/0)`.
+pub fn free_cells(world: &World) -> Vec<Position> {
+    (0..world.height() as i32)
+        .flat_map(|y| (0..world.width() as i32).map(move |x| Position::new(x, y)))
+        .filter(|position| world.tile(position).is_none())
+        .collect()
+}
+
+/// Puts up to `count` charge pads on random free cells chosen by `seed`.
+///
+/// Returns where they went.
+pub fn scatter_charge_pads(world: &mut World, count: usize, seed: u64) -> Vec<Position> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut free = free_cells(world);
+    free.shuffle(&mut rng);
+    free.truncate(count);
+    for position in &free {
+        world.add_tile(position.clone(), Tile::ChargePad);
+    }
+    free
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const LAYOUTS: [Layout; 3] = [Layout::Maze, Layout::Rooms, Layout::Caves];
+
+    /// Free cells reachable from the first free cell.
+    fn reachable(world: &World) -> HashSet<Position> {
+        let free: HashSet<Position> = free_cells(world).into_iter().collect();
+        let mut seen = HashSet::new();
+        let mut open: VecDeque<Position> = free_cells(world).into_iter().take(1).collect();
+        while let Some(cell) = open.pop_front() {
+            if !seen.insert(cell.clone()) {
+                continue;
+            }
+            for (dx, dy) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                let next = Position::new(cell.x + dx, cell.y + dy);
+                if free.contains(&next) {
+                    open.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn every_free_cell_is_reachable() {
+        for layout in LAYOUTS {
+            for seed in 0..20 {
+                let world = generate(layout, 40, 20, seed);
+                let free = free_cells(&world);
+                assert!(
+                    free.len() > 100,
+                    "{layout:?} with seed {seed} is too closed"
+                );
+                assert_eq!(
+                    reachable(&world).len(),
+                    free.len(),
+                    "{layout:?} with seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_are_reproducible() {
+        for layout in LAYOUTS {
+            let world = free_cells(&generate(layout, 30, 15, 42));
+            assert_eq!(free_cells(&generate(layout, 30, 15, 42)), world);
+            assert_ne!(free_cells(&generate(layout, 30, 15, 43)), world);
+        }
+    }
+
+    #[test]
+    fn tiny_worlds_keep_a_free_cell() {
+        for layout in LAYOUTS {
+            for (width, height) in [(1, 1), (2, 3), (5, 1)] {
+                let world = generate(layout, width, height, 7);
+                assert!(
+                    !free_cells(&world).is_empty(),
+                    "{layout:?} {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn charge_pads_go_on_free_cells() {
+        let mut world = generate(Layout::Caves, 20, 10, 1);
+        let free = free_cells(&world).len();
+        let pads = scatter_charge_pads(&mut world, 3, 1);
+        assert_eq!(pads.len(), 3);
+        assert!(
+            pads.iter()
+                .all(|pad| world.tile(pad) == Some(&Tile::ChargePad))
+        );
+        assert_eq!(free_cells(&world).len(), free - 3);
+        assert_eq!(scatter_charge_pads(&mut world, free, 1).len(), free - 3);
+    }
+}
@@ -0,0 +1,61 @@
+//! Compass heading of a robot.
+//!
+//! The world uses a mathematical orientation: `x` grows to the east
+//! and `y` grows to the north.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The direction a robot is facing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Heading {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    /// Heading after a quarter turn counter-clockwise.
+    pub fn turn_left(self) -> Self {
+        match self {
+            Heading::North => Heading::West,
+            Heading::West => Heading::South,
+            Heading::South => Heading::East,
+            Heading::East => Heading::North,
+        }
+    }
+
+    /// Heading after a quarter turn clockwise.
+    pub fn turn_right(self) -> Self {
+        match self {
+            Heading::North => Heading::East,
+            Heading::East => Heading::South,
+            Heading::South => Heading::West,
+            Heading::West => Heading::North,
+        }
+    }
+
+    /// Unit step `(dx, dy)` when moving one cell along this heading.
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Heading::North => (0, 1),
+            Heading::East => (1, 0),
+            Heading::South => (0, -1),
+            Heading::West => (-1, 0),
+        }
+    }
+}
+
+impl fmt::Display for Heading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Heading::North => "N",
+            Heading::East => "E",
+            Heading::South => "S",
+            Heading::West => "W",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -34,5 +34,46 @@ mod cli;
 /// Common position struct for world and robot
 mod position;
 
+/// Compass heading of a robot
+mod heading;
+
 /// World combining everything
 mod world;
+
+/// Fixed-rate tick engine advancing the world.
+mod simulation;
+
+/// Queued robot commands and program files.
+mod command;
+
+/// Parser and interpreter of the robot programming language.
+mod dsl;
+
+/// Rhai controller scripts deciding what idle robots do next.
+mod controller;
+
+/// Ray-cast range sensors of robots.
+mod sensor;
+
+/// Occupancy-grid belief maps built from sensor readings.
+mod mapping;
+
+/// Frontier-based autonomous exploration of belief maps.
+mod explore;
+
+/// Seeded generators of mazes, rooms and caves.
+mod generate;
+
+/// Coverage route planning for robots that must visit every free cell.
+///
+/// Public like [`planner`], which it builds on.
+pub mod coverage;
+
+/// A* path planning on the world grid.
+///
+/// Public so that tools built on top of the crate can plan routes.
+pub mod planner;
+
+/// The world and positions the public planners work on.
+pub use position::Position;
+pub use world::{Tile, World};
@@ -1,6 +1,7 @@
 use rusty_the_robot::run::run; // - in crate name translates to _
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
-    run().await;
+async fn main() -> ExitCode {
+    run().await
 }
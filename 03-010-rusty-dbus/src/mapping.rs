@@ -0,0 +1,265 @@
+//! Occupancy-grid belief maps.
+//!
+//! Every robot builds its own [`crate::mapping::OccupancyGrid`] from what its
+//! lidar actually observed, see [`crate::sensor`]. Cells start unknown; every
+//! beam passing a cell is evidence for free space, every beam ending in a
+//! wall evidence for an obstacle. Evidence is accumulated as log-odds, so
+//! single wrong readings and changing doors are tolerated.
+//!
+//! Robots are not mapped as obstacles since they move; cells behind them
+//! stay as they were. The tiles of the [`crate::world::World`] are ground
+//! truth the maps can be compared with.
+
+use crate::heading::Heading;
+use crate::position::Position;
+use crate::sensor::{Hit, Ray, Reading, beam_direction};
+use serde::{Deserialize, Serialize};
+
+/// Log-odds added to a cell a beam ended in.
+pub const LOG_ODDS_OCCUPIED: f32 = 0.85;
+
+/// Log-odds added to a cell a beam passed.
+pub const LOG_ODDS_FREE: f32 = -0.4;
+
+/// Log-odds are clamped to this magnitude so the map can still change its mind.
+pub const LOG_ODDS_LIMIT: f32 = 3.5;
+
+/// Log-odds magnitude from which a cell counts as known.
+pub const LOG_ODDS_KNOWN: f32 = 0.2;
+
+/// What a robot believes about a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Belief {
+    Unknown,
+    Free,
+    Occupied,
+}
+
+/// A robot's belief about the world grid, as log-odds of every cell being occupied.
+///
+/// Saved grids whose log-odds do not fit their size are rejected on load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawGrid")]
+pub struct OccupancyGrid {
+    width: u32,
+    height: u32,
+    /// Row by row, starting at `y = 0`.
+    log_odds: Vec<f32>,
+}
+
+/// An [`OccupancyGrid`] as saved, before its size is checked.
+#[derive(Deserialize)]
+struct RawGrid {
+    width: u32,
+    height: u32,
+    log_odds: Vec<f32>,
+}
+
+impl TryFrom<RawGrid> for OccupancyGrid {
+    type Error = String;
+
+    fn try_from(raw: RawGrid) -> Result<Self, Self::Error> {
+        let cells = raw.width as usize * raw.height as usize;
+        if raw.log_odds.len() != cells {
+            return Err(format!(
+                "belief map of {}x{} cells has {} log-odds",
+                raw.width,
+                raw.height,
+                raw.log_odds.len()
+            ));
+        }
+        Ok(Self {
+            width: raw.width,
+            height: raw.height,
+            log_odds: raw.log_odds,
+        })
+    }
+}
+
+impl OccupancyGrid {
+    /// Creates a map of `width` x `height` unknown cells.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            log_odds: vec![0.0; width as usize * height as usize],
+        }
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        let x = u32::try_from(position.x).ok().filter(|&x| x < self.width)?;
+        let y = u32::try_from(position.y).ok().filter(|&y| y < self.height)?;
+        Some(y as usize * self.width as usize + x as usize)
+    }
+
+    /// Log-odds of `position` being occupied, `0` (no idea) outside the map.
+    pub fn log_odds(&self, position: &Position) -> f32 {
+        self.index(position)
+            .map_or(0.0, |index| self.log_odds[index])
+    }
+
+    /// Probability of `position` being occupied.
+    pub fn probability(&self, position: &Position) -> f64 {
+        1.0 - 1.0 / (1.0 + f64::from(self.log_odds(position)).exp())
+    }
+
+    pub fn belief(&self, position: &Position) -> Belief {
+        let log_odds = self.log_odds(position);
+        if log_odds >= LOG_ODDS_KNOWN {
+            Belief::Occupied
+        } else if log_odds <= -LOG_ODDS_KNOWN {
+            Belief::Free
+        } else {
+            Belief::Unknown
+        }
+    }
+
+    /// Adds `evidence` to the log-odds of `position`, ignoring cells outside the map.
+    pub fn update(&mut self, position: &Position, evidence: f32) {
+        if let Some(index) = self.index(position) {
+            let log_odds = &mut self.log_odds[index];
+            *log_odds = (*log_odds + evidence).clamp(-LOG_ODDS_LIMIT, LOG_ODDS_LIMIT);
+        }
+    }
+
+    /// Adds the lidar `readings` taken at `origin` facing `heading`.
+    ///
+    /// The robot's own cell and every cell a beam crossed become more likely
+    /// free, the cell a beam hit a wall in more likely occupied.
+    pub fn integrate(&mut self, origin: &Position, heading: Heading, readings: &[Reading]) {
+        self.update(origin, LOG_ODDS_FREE);
+        for reading in readings {
+            let direction = beam_direction(heading, reading.angle);
+            for (cell, distance) in Ray::new(origin, direction) {
+                if reading.cell.as_ref() == Some(&cell) {
+                    if reading.hit == Hit::Wall {
+                        self.update(&cell, LOG_ODDS_OCCUPIED);
+                    }
+                    break;
+                }
+                if distance >= reading.distance {
+                    break;
+                }
+                self.update(&cell, LOG_ODDS_FREE);
+            }
+        }
+    }
+
+    /// Share of the cells that are not [`Belief::Unknown`], in percent.
+    pub fn coverage(&self) -> f64 {
+        if self.log_odds.is_empty() {
+            return 100.0;
+        }
+        100.0 * self.known().count() as f64 / self.log_odds.len() as f64
+    }
+
+    /// All cells that are not [`Belief::Unknown`].
+    pub fn known(&self) -> impl Iterator<Item = (Position, Belief)> + '_ {
+        (0..self.height as i32)
+            .flat_map(move |y| (0..self.width as i32).map(move |x| Position::new(x, y)))
+            .map(|position| {
+                let belief = self.belief(&position);
+                (position, belief)
+            })
+            .filter(|(_, belief)| *belief != Belief::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::Lidar;
+    use crate::world::{Tile, World};
+
+    /// 6x6 world, `rusty` at (1/1) facing north, a wall at (1/4).
+    fn world() -> World {
+        let mut world = World::new(6, 6);
+        world.add_tile(Position::new(1, 4), Tile::Wall);
+        world.add_robot_new(String::from("rusty"));
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            robot.position = Position::new(1, 1);
+        }
+        world
+    }
+
+    fn observe(world: &World, grid: &mut OccupancyGrid, lidar: Lidar) {
+        if let (Some(robot), Ok(readings)) = (world.get_robot("rusty"), lidar.scan(world, "rusty"))
+        {
+            grid.integrate(&robot.position, robot.heading, &readings);
+        }
+    }
+
+    #[test]
+    fn integrate_marks_free_and_occupied_cells() {
+        let world = world();
+        let mut grid = OccupancyGrid::new(6, 6);
+        let lidar = Lidar {
+            beams: 4,
+            range: 10.0,
+        };
+        observe(&world, &mut grid, lidar);
+
+        for free in [(1, 1), (1, 2), (1, 3), (0, 1), (2, 1), (5, 1), (1, 0)] {
+            assert_eq!(grid.belief(&Position::new(free.0, free.1)), Belief::Free);
+        }
+        assert_eq!(grid.belief(&Position::new(1, 4)), Belief::Occupied);
+        // behind the wall and off the beams nothing is known
+        assert_eq!(grid.belief(&Position::new(1, 5)), Belief::Unknown);
+        assert_eq!(grid.belief(&Position::new(3, 3)), Belief::Unknown);
+        assert_eq!(grid.known().count(), 10);
+    }
+
+    #[test]
+    fn repeated_evidence_is_clamped_and_can_be_revised() {
+        let mut grid = OccupancyGrid::new(2, 2);
+        let cell = Position::new(1, 1);
+        for _ in 0..20 {
+            grid.update(&cell, LOG_ODDS_OCCUPIED);
+        }
+        assert_eq!(grid.log_odds(&cell), LOG_ODDS_LIMIT);
+        assert!(grid.probability(&cell) > 0.95);
+        for _ in 0..20 {
+            grid.update(&cell, LOG_ODDS_FREE);
+        }
+        assert_eq!(grid.belief(&cell), Belief::Free);
+        // outside the map nothing happens
+        grid.update(&Position::new(-1, 0), LOG_ODDS_OCCUPIED);
+        assert_eq!(grid.belief(&Position::new(-1, 0)), Belief::Unknown);
+    }
+
+    #[test]
+    fn grid_survives_serialization() -> Result<(), serde_json::Error> {
+        let world = world();
+        let mut grid = OccupancyGrid::new(6, 6);
+        observe(&world, &mut grid, Lidar::default());
+        let restored: OccupancyGrid = serde_json::from_str(&serde_json::to_string(&grid)?)?;
+        assert_eq!(restored, grid);
+        Ok(())
+    }
+
+    #[test]
+    fn grids_of_the_wrong_length_are_rejected() {
+        let source = r#"{
+            "format_version": 1, "height": 5, "width": 5, "tiles": [],
+            "robots": [{
+                "name": "rusty", "position": {"x": 1, "y": 1},
+                "belief": {"width": 5, "height": 5, "log_odds": []}
+            }]
+        }"#;
+        let error = World::from_json(source).err().map(|e| e.to_string());
+        assert_eq!(
+            error.as_deref(),
+            Some("belief map of 5x5 cells has 0 log-odds at line 6 column 13")
+        );
+    }
+}
@@ -5,16 +5,20 @@
 //! It provides the foundation for controlling objects that
 //! can change their position or state in a directional manner.
 
+use crate::position::Position;
+
 /// Defines the direction in which a [`crate::moveable::Moveable`] entity can move.
 ///
 /// Each variant represents a logical movement or orientation change.
+/// Movements are relative to the entity's current heading.
 ///
 /// # Examples
-/// ```
-/// use robot_control::moveable::Direction;
+/// ```ignore
+/// use rusty_the_robot::moveable::Direction;
 ///
 /// let forward = Direction::Forward { step: 2 };
 /// ```
+#[derive(Debug, Clone, PartialEq)]
 pub enum Direction {
     /// Move forward by a specific number of steps.
     Forward { step: i32 },
@@ -27,15 +31,46 @@ pub enum Direction {
 
     /// Move one unit to the right.
     Right,
+
+    /// Turn a quarter to the left without moving.
+    TurnLeft,
+
+    /// Turn a quarter to the right without moving.
+    TurnRight,
 }
 
 /// Represents potential errors during movement operations.
 ///
 /// Returned when movement cannot be completed successfully.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MovementError {
     // pub as its return type of move_robot which is also public
     TooFar,
+
+    /// The requested step count is negative.
+    InvalidStep,
+
+    /// A wall or closed door was in the way at the given position.
+    Blocked {
+        at: Position,
+    },
+
+    /// The move would leave the world grid.
+    OutOfBounds,
+
+    /// Another robot already occupies the target cell.
+    Collision {
+        with: String,
+    },
+
+    /// The battery is too low for the move.
+    BatteryEmpty,
+
+    /// No robot with the given name exists.
+    UnknownRobot(String),
+
+    /// The robot should charge but does not stand on a charge pad.
+    NoChargePad,
 }
 
 /// Trait defining directional movement behavior.
@@ -43,8 +78,8 @@ pub enum MovementError {
 /// Implementors can define how a type responds to movement commands.
 ///
 /// # Example
-/// ```
-/// use robot_control::moveable::{Direction, Moveable, MovementError};
+/// ```ignore
+/// use rusty_the_robot::moveable::{Direction, Moveable, MovementError};
 ///
 /// struct Dummy;
 ///
@@ -0,0 +1,314 @@
+//! Shortest path planning on the [`crate::world::World`] grid.
+//!
+//! The [`crate::planner::Planner`] runs A* over the world's tiles, treating
+//! [`crate::world::Tile::Wall`], closed doors and the world border as blocked. The found
+//! [`crate::planner::Path`] can be turned into [`crate::moveable::Direction`]s
+//! and fed to [`crate::moveable::Moveable::move_robot`].
+
+use crate::heading::Heading;
+use crate::moveable::Direction;
+use crate::position::Position;
+use crate::world::{Tile, World};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+pub mod multi;
+
+/// Cost of a straight step onto a tile with cost 1.
+const STRAIGHT: u32 = 100;
+/// Cost of a diagonal step onto a tile with cost 1 (roughly `100 * sqrt(2)`).
+const DIAGONAL: u32 = 141;
+
+/// Which neighbours of a cell can be reached in one step.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// North, east, south and west.
+    #[default]
+    Four,
+    /// Additionally the four diagonals. Corners of walls are never cut.
+    Eight,
+}
+
+/// Reasons why no path could be planned.
+#[derive(Debug, PartialEq)]
+pub enum PlanError {
+    /// Start or goal lies outside the world.
+    OutOfBounds(Position),
+    /// Start or goal is a wall or closed door.
+    Blocked(Position),
+    /// There is no obstacle-free connection between start and goal.
+    Unreachable,
+}
+
+/// A planned path including start and goal cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// Visited cells in order, including start and goal.
+    pub cells: Vec<Position>,
+    /// Summed traversal cost, one straight step on a cost 1 tile counts as `1.0`.
+    pub cost: f64,
+}
+
+/// A* planner with configurable connectivity and tile costs.
+///
+/// Tiles without an explicit cost (and unset cells) cost `1` to enter.
+#[derive(Debug, Default)]
+pub struct Planner {
+    /// Neighbourhood used for expanding cells.
+    pub connectivity: Connectivity,
+    costs: HashMap<Tile, u32>,
+}
+
+impl Planner {
+    /// Creates a planner with default tile costs.
+    pub fn new(connectivity: Connectivity) -> Self {
+        Self {
+            connectivity,
+            costs: HashMap::new(),
+        }
+    }
+
+    /// Sets the cost for entering `tile`. A cost of `0` is raised to `1`.
+    ///
+    /// Walls stay impassable regardless of their cost.
+    pub fn set_cost(&mut self, tile: Tile, cost: u32) {
+        self.costs.insert(tile, cost.max(1));
+    }
+
+    fn cost(&self, world: &World, position: &Position) -> Option<u32> {
+        if !world.in_bounds(position) || world.is_blocked(position) {
+            return None;
+        }
+        Some(
+            world
+                .tile(position)
+                .and_then(|tile| self.costs.get(tile))
+                .copied()
+                .unwrap_or(1),
+        )
+    }
+
+    fn min_cost(&self) -> u32 {
+        self.costs.values().copied().chain([1]).min().unwrap_or(1)
+    }
+
+    fn heuristic(&self, from: &Position, to: &Position) -> u32 {
+        let dx = from.x.abs_diff(to.x);
+        let dy = from.y.abs_diff(to.y);
+        let estimate = match self.connectivity {
+            Connectivity::Four => (dx + dy) * STRAIGHT,
+            Connectivity::Eight => dx.min(dy) * DIAGONAL + dx.abs_diff(dy) * STRAIGHT,
+        };
+        estimate * self.min_cost()
+    }
+
+    fn neighbours(&self, world: &World, cell: &Position) -> Vec<(Position, u32)> {
+        let mut neighbours = Vec::with_capacity(8);
+        let free = |dx: i32, dy: i32| self.cost(world, &Position::new(cell.x + dx, cell.y + dy));
+        for (dx, dy) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+            if let Some(cost) = free(dx, dy) {
+                neighbours.push((Position::new(cell.x + dx, cell.y + dy), cost * STRAIGHT));
+            }
+        }
+        if self.connectivity == Connectivity::Eight {
+            for (dx, dy) in [(1, 1), (1, -1), (-1, -1), (-1, 1)] {
+                // no corner cutting: both orthogonal cells must be free
+                if free(dx, 0).is_none() || free(0, dy).is_none() {
+                    continue;
+                }
+                if let Some(cost) = free(dx, dy) {
+                    neighbours.push((Position::new(cell.x + dx, cell.y + dy), cost * DIAGONAL));
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Finds the cheapest path from `start` to `goal`.
+    ///
+    /// # Errors
+    /// Returns a [`PlanError`] if start or goal are not traversable
+    /// or there is no connection between them.
+    pub fn plan(
+        &self,
+        world: &World,
+        start: &Position,
+        goal: &Position,
+    ) -> Result<Path, PlanError> {
+        for position in [start, goal] {
+            if !world.in_bounds(position) {
+                return Err(PlanError::OutOfBounds(position.clone()));
+            }
+            if world.is_blocked(position) {
+                return Err(PlanError::Blocked(position.clone()));
+            }
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Position, Position> = HashMap::new();
+        let mut best: HashMap<Position, u32> = HashMap::new();
+        best.insert(start.clone(), 0);
+        open.push(Reverse((self.heuristic(start, goal), 0, start.x, start.y)));
+
+        while let Some(Reverse((_, g, x, y))) = open.pop() {
+            let cell = Position::new(x, y);
+            if cell == *goal {
+                let mut cells = vec![cell];
+                while let Some(previous) = cells.last().and_then(|c| came_from.get(c)) {
+                    cells.push(previous.clone());
+                }
+                cells.reverse();
+                return Ok(Path {
+                    cells,
+                    cost: f64::from(g) / f64::from(STRAIGHT),
+                });
+            }
+            if best.get(&cell).is_some_and(|&known| known < g) {
+                continue; // stale heap entry
+            }
+            for (next, step) in self.neighbours(world, &cell) {
+                let tentative = g + step;
+                if best.get(&next).is_none_or(|&known| tentative < known) {
+                    best.insert(next.clone(), tentative);
+                    came_from.insert(next.clone(), cell.clone());
+                    let f = tentative + self.heuristic(&next, goal);
+                    open.push(Reverse((f, tentative, next.x, next.y)));
+                }
+            }
+        }
+        Err(PlanError::Unreachable)
+    }
+}
+
+/// The move taking a robot facing `heading` one cell along `delta`,
+/// which has to point to an orthogonal neighbour.
+pub fn step(heading: Heading, delta: (i32, i32)) -> Direction {
+    if delta == heading.delta() {
+        Direction::Forward { step: 1 }
+    } else if delta == heading.turn_left().delta() {
+        Direction::Left
+    } else if delta == heading.turn_right().delta() {
+        Direction::Right
+    } else {
+        Direction::Backwards
+    }
+}
+
+impl Path {
+    /// Converts the path into movement commands for a robot facing `heading`.
+    ///
+    /// The robot keeps its heading and strafes where needed. Diagonal steps
+    /// are split into two orthogonal moves, which is always possible because
+    /// the planner does not cut corners. Consecutive forward moves are merged
+    /// up to the robot's limit of three steps.
+    pub fn directions(&self, heading: Heading) -> Vec<Direction> {
+        let mut directions: Vec<Direction> = Vec::new();
+        for pair in self.cells.windows(2) {
+            let dx = pair[1].x - pair[0].x;
+            let dy = pair[1].y - pair[0].y;
+            let steps = match (dx, dy) {
+                (0, _) | (_, 0) => vec![(dx, dy)],
+                _ => vec![(dx, 0), (0, dy)],
+            };
+            for delta in steps {
+                let direction = step(heading, delta);
+                match (directions.last_mut(), &direction) {
+                    (Some(Direction::Forward { step }), Direction::Forward { .. }) if *step < 3 => {
+                        *step += 1
+                    }
+                    _ => directions.push(direction),
+                }
+            }
+        }
+        directions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moveable::Moveable;
+    use crate::robot::Robot;
+
+    /// 5x5 world with a wall column at x = 2 that is open at y = 4.
+    fn world_with_gap() -> World {
+        let mut world = World::new(5, 5);
+        for y in 0..4 {
+            world.add_tile(Position::new(2, y), Tile::Wall);
+        }
+        world
+    }
+
+    #[test]
+    fn plan_around_wall() -> Result<(), PlanError> {
+        let world = world_with_gap();
+        let planner = Planner::default();
+        let path = planner.plan(&world, &Position::new(0, 0), &Position::new(4, 0))?;
+        assert_eq!(path.cells.first(), Some(&Position::new(0, 0)));
+        assert_eq!(path.cells.last(), Some(&Position::new(4, 0)));
+        assert!(path.cells.contains(&Position::new(2, 4)));
+        assert_eq!(path.cost, 12.0);
+        Ok(())
+    }
+
+    #[test]
+    fn plan_eight_connected_is_shorter() -> Result<(), PlanError> {
+        let world = World::new(5, 5);
+        let four = Planner::new(Connectivity::Four).plan(
+            &world,
+            &Position::new(0, 0),
+            &Position::new(3, 3),
+        )?;
+        let eight = Planner::new(Connectivity::Eight).plan(
+            &world,
+            &Position::new(0, 0),
+            &Position::new(3, 3),
+        )?;
+        assert_eq!(four.cells.len(), 7);
+        assert_eq!(eight.cells.len(), 4);
+        assert!(eight.cost < four.cost);
+        Ok(())
+    }
+
+    #[test]
+    fn plan_prefers_cheap_tiles() -> Result<(), PlanError> {
+        let mut world = World::new(3, 3);
+        world.add_tile(Position::new(1, 0), Tile::Empty);
+        let mut planner = Planner::default();
+        planner.set_cost(Tile::Empty, 10);
+        let path = planner.plan(&world, &Position::new(0, 0), &Position::new(2, 0))?;
+        assert!(!path.cells.contains(&Position::new(1, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn plan_unreachable() {
+        let mut world = world_with_gap();
+        world.add_tile(Position::new(2, 4), Tile::Wall);
+        assert_eq!(
+            Planner::default().plan(&world, &Position::new(0, 0), &Position::new(4, 0)),
+            Err(PlanError::Unreachable)
+        );
+        assert_eq!(
+            Planner::default().plan(&world, &Position::new(0, 0), &Position::new(2, 0)),
+            Err(PlanError::Blocked(Position::new(2, 0)))
+        );
+    }
+
+    #[test]
+    fn directions_follow_path() -> Result<(), PlanError> {
+        let world = world_with_gap();
+        let path = Planner::new(Connectivity::Eight).plan(
+            &world,
+            &Position::new(0, 0),
+            &Position::new(4, 0),
+        )?;
+        let mut robot = Robot::new(String::from("rusty"));
+        robot.move_robot(Direction::TurnRight).ok();
+        for direction in path.directions(robot.heading) {
+            assert!(robot.move_robot(direction).is_ok());
+        }
+        assert_eq!(robot.position, Position::new(4, 0));
+        Ok(())
+    }
+}
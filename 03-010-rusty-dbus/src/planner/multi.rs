@@ -0,0 +1,429 @@
+//! Multi-agent path finding by prioritized planning.
+//!
+//! The [`crate::planner::multi::MultiPlanner`] routes several robots at once.
+//! Robots are planned one after another with space-time A*: every planned
+//! robot reserves the cells it stands on at each tick, later robots plan
+//! around these reservations and may wait in place. A robot that arrived
+//! stays on its goal. Robots that are not routed are obstacles.
+//!
+//! The tick engine moves robots one after another, not all at once, see
+//! [`crate::world::World::step`]. So that the plans hold in any order, a
+//! robot never enters a cell another robot stands on in the same, the
+//! previous or the next tick. This rules out swaps as well as following
+//! right behind another robot.
+//!
+//! When a robot finds no plan, it is given the highest priority and all
+//! robots are planned again, up to once per robot. Prioritized planning is
+//! fast but not complete: where a robot would first have to make room for
+//! one planned after it, e.g. in a dead end, no plans may be found although
+//! there are some.
+
+use super::{PlanError, Planner, step};
+use crate::command::Command;
+use crate::heading::Heading;
+use crate::position::Position;
+use crate::world::World;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Default number of ticks a plan may take.
+pub const DEFAULT_MAX_TICKS: usize = 256;
+
+/// A robot to route and where to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    /// Name of the robot.
+    pub name: String,
+    /// Cell the robot has to end up on.
+    pub goal: Position,
+}
+
+/// Reasons why no plans could be found.
+#[derive(Debug, PartialEq)]
+pub enum MultiPlanError {
+    /// There is no robot with that name.
+    UnknownRobot(String),
+    /// The robot could not reach its goal even if it were alone.
+    Plan {
+        /// Name of the robot.
+        name: String,
+        /// Why its route could not be planned.
+        error: PlanError,
+    },
+    /// Several robots were sent to the same cell.
+    SharedGoal(Position),
+    /// The robots block each other within the allowed number of ticks.
+    NoSolution,
+}
+
+/// Collision-free, time-indexed plans for several robots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// Per robot its cell at every tick, starting where it stands and ending
+    /// on its goal. Afterwards the robot stays there.
+    pub plans: Vec<(String, Vec<Position>)>,
+}
+
+impl Schedule {
+    /// Ticks until the last robot arrived.
+    pub fn makespan(&self) -> usize {
+        self.plans
+            .iter()
+            .map(|(_, cells)| cells.len().saturating_sub(1))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Ticks all robots need together until they arrived.
+    pub fn sum_of_costs(&self) -> usize {
+        self.plans
+            .iter()
+            .map(|(_, cells)| cells.len().saturating_sub(1))
+            .sum()
+    }
+
+    /// Whether no two robots ever share a cell, swap cells or follow each
+    /// other directly, see the [module docs](self).
+    pub fn is_collision_free(&self) -> bool {
+        let at = |cells: &[Position], tick: usize| cells[tick.min(cells.len() - 1)].clone();
+        let ticks = self.makespan() + 1;
+        self.plans.iter().enumerate().all(|(index, (_, a))| {
+            self.plans[index + 1..].iter().all(|(_, b)| {
+                (0..ticks).all(|tick| {
+                    at(a, tick) != at(b, tick)
+                        && at(a, tick + 1) != at(b, tick)
+                        && at(a, tick) != at(b, tick + 1)
+                })
+            })
+        })
+    }
+}
+
+/// Turns the cells of a plan into commands for a robot facing `heading`,
+/// one cell or wait per tick.
+pub fn commands(cells: &[Position], heading: Heading) -> Vec<Command> {
+    let mut commands: Vec<Command> = Vec::new();
+    for pair in cells.windows(2) {
+        let delta = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        if delta != (0, 0) {
+            commands.push(step(heading, delta).into());
+            continue;
+        }
+        match commands.last_mut() {
+            Some(Command::Wait { ticks }) => *ticks += 1,
+            _ => commands.push(Command::Wait { ticks: 1 }),
+        }
+    }
+    commands
+}
+
+/// Cells taken by the robots planned so far.
+#[derive(Default)]
+struct Reservations {
+    /// A robot stands on the cell at the tick.
+    taken: HashSet<(Position, usize)>,
+    /// Last tick a robot passes the cell.
+    latest: HashMap<Position, usize>,
+    /// A robot stays on the cell from the tick on.
+    parked: HashMap<Position, usize>,
+}
+
+impl Reservations {
+    fn reserve(&mut self, cells: &[Position]) {
+        for (tick, cell) in cells.iter().enumerate() {
+            self.taken.insert((cell.clone(), tick));
+            let latest = self.latest.entry(cell.clone()).or_default();
+            *latest = (*latest).max(tick);
+        }
+        if let Some(goal) = cells.last() {
+            self.parked.insert(goal.clone(), cells.len() - 1);
+        }
+    }
+
+    /// Whether a robot may stand on `cell` at `tick`.
+    fn is_free(&self, cell: &Position, tick: usize) -> bool {
+        (tick.saturating_sub(1)..=tick + 1).all(|t| !self.taken.contains(&(cell.clone(), t)))
+            && self.parked.get(cell).is_none_or(|&from| tick + 1 < from)
+    }
+
+    /// Whether a robot may stay on `cell` for good from `tick` on.
+    fn is_free_from(&self, cell: &Position, tick: usize) -> bool {
+        !self.parked.contains_key(cell) && self.latest.get(cell).is_none_or(|&last| last + 1 < tick)
+    }
+}
+
+/// Prioritized planner with a space-time reservation table.
+#[derive(Debug)]
+pub struct MultiPlanner {
+    /// Most ticks a single plan may take.
+    pub max_ticks: usize,
+}
+
+impl Default for MultiPlanner {
+    fn default() -> Self {
+        Self {
+            max_ticks: DEFAULT_MAX_TICKS,
+        }
+    }
+}
+
+impl MultiPlanner {
+    /// Plans collision-free routes for all `tasks`, earlier tasks first.
+    ///
+    /// # Errors
+    /// Returns a [`MultiPlanError`] if a task is impossible on its own or
+    /// the robots keep blocking each other.
+    pub fn plan(&self, world: &World, tasks: &[Task]) -> Result<Schedule, MultiPlanError> {
+        let mut goals = HashSet::new();
+        for task in tasks {
+            let robot = world
+                .get_robot(&task.name)
+                .ok_or_else(|| MultiPlanError::UnknownRobot(task.name.clone()))?;
+            if !goals.insert(&task.goal) {
+                return Err(MultiPlanError::SharedGoal(task.goal.clone()));
+            }
+            Planner::default()
+                .plan(world, &robot.position, &task.goal)
+                .map_err(|error| MultiPlanError::Plan {
+                    name: task.name.clone(),
+                    error,
+                })?;
+        }
+        let routed: HashSet<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        let obstacles: HashSet<Position> = world
+            .robots()
+            .iter()
+            .filter(|robot| !robot.ghost && !routed.contains(robot.name.as_str()))
+            .map(|robot| robot.position.clone())
+            .collect();
+
+        let mut order: Vec<&Task> = tasks.iter().collect();
+        for _ in 0..tasks.len().max(1) {
+            match self.plan_in_order(world, &order, &obstacles) {
+                Ok(plans) => {
+                    let plans = tasks
+                        .iter()
+                        .filter_map(|task| {
+                            plans
+                                .iter()
+                                .find(|(name, _)| *name == task.name)
+                                .cloned()
+                        })
+                        .collect();
+                    return Ok(Schedule { plans });
+                }
+                Err(failed) if failed > 0 => {
+                    let task = order.remove(failed);
+                    order.insert(0, task);
+                }
+                Err(_) => break,
+            }
+        }
+        Err(MultiPlanError::NoSolution)
+    }
+
+    /// Plans `order` one robot after another, or returns the index of the first robot without a plan.
+    fn plan_in_order(
+        &self,
+        world: &World,
+        order: &[&Task],
+        obstacles: &HashSet<Position>,
+    ) -> Result<Vec<(String, Vec<Position>)>, usize> {
+        let mut reservations = Reservations::default();
+        // robots planned later need their start cell until they can move away
+        let mut waiting: HashSet<Position> = order
+            .iter()
+            .filter_map(|task| world.get_robot_position(&task.name))
+            .collect();
+        let mut plans = Vec::new();
+        for (index, task) in order.iter().enumerate() {
+            let start = world.get_robot_position(&task.name).ok_or(index)?;
+            waiting.remove(&start);
+            let cells = self
+                .plan_one(world, &start, &task.goal, &reservations, |cell, tick| {
+                    obstacles.contains(cell) || (tick <= 1 && waiting.contains(cell))
+                })
+                .ok_or(index)?;
+            reservations.reserve(&cells);
+            plans.push((task.name.clone(), cells));
+        }
+        Ok(plans)
+    }
+
+    /// Space-time A* from `start` to `goal` around `reservations` and cells
+    /// `blocked` at a tick.
+    fn plan_one(
+        &self,
+        world: &World,
+        start: &Position,
+        goal: &Position,
+        reservations: &Reservations,
+        blocked: impl Fn(&Position, usize) -> bool,
+    ) -> Option<Vec<Position>> {
+        let heuristic = |cell: &Position| (cell.x.abs_diff(goal.x) + cell.y.abs_diff(goal.y)) as usize;
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<(Position, usize), Position> = HashMap::new();
+        let mut closed: HashSet<(Position, usize)> = HashSet::new();
+        open.push(Reverse((heuristic(start), 0, start.x, start.y)));
+
+        while let Some(Reverse((_, tick, x, y))) = open.pop() {
+            let cell = Position::new(x, y);
+            if !closed.insert((cell.clone(), tick)) {
+                continue;
+            }
+            if cell == *goal && reservations.is_free_from(&cell, tick) {
+                let mut cells = vec![cell];
+                for t in (1..=tick).rev() {
+                    let previous = came_from.get(&(cells[cells.len() - 1].clone(), t))?;
+                    cells.push(previous.clone());
+                }
+                cells.reverse();
+                return Some(cells);
+            }
+            if tick >= self.max_ticks {
+                continue;
+            }
+            let next_tick = tick + 1;
+            let moves = [(0, 0), (0, 1), (1, 0), (0, -1), (-1, 0)]
+                .map(|(dx, dy)| Position::new(cell.x + dx, cell.y + dy));
+            for next in moves {
+                if !world.in_bounds(&next)
+                    || world.is_blocked(&next)
+                    || blocked(&next, next_tick)
+                    || !reservations.is_free(&next, next_tick)
+                    || closed.contains(&(next.clone(), next_tick))
+                {
+                    continue;
+                }
+                let key = (next.clone(), next_tick);
+                if came_from.contains_key(&key) {
+                    continue;
+                }
+                came_from.insert(key, cell.clone());
+                open.push(Reverse((
+                    next_tick + heuristic(&next),
+                    next_tick,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Tile;
+
+    /// Corridor 5 cells long with a pocket above its middle.
+    fn corridor() -> World {
+        let mut world = World::new(2, 5);
+        for x in [0, 1, 3, 4] {
+            world.add_tile(Position::new(x, 1), Tile::Wall);
+        }
+        for (name, x) in [("rusty", 0), ("karl", 4)] {
+            world.add_robot_new(name.to_string());
+            if let Some(robot) = world.get_robot_mut(name) {
+                robot.position = Position::new(x, 0);
+            }
+        }
+        world
+    }
+
+    fn task(name: &str, x: i32, y: i32) -> Task {
+        Task {
+            name: name.to_string(),
+            goal: Position::new(x, y),
+        }
+    }
+
+    #[test]
+    fn robots_swap_places_on_two_lanes() -> Result<(), MultiPlanError> {
+        let mut world = World::new(2, 5);
+        for (name, x) in [("rusty", 0), ("karl", 4)] {
+            world.add_robot_new(name.to_string());
+            if let Some(robot) = world.get_robot_mut(name) {
+                robot.position = Position::new(x, 0);
+            }
+        }
+        let tasks = [task("rusty", 4, 0), task("karl", 0, 0)];
+        let schedule = MultiPlanner::default().plan(&world, &tasks)?;
+        assert!(schedule.is_collision_free());
+        // karl gives way on the upper lane
+        assert!(
+            schedule
+                .plans
+                .iter()
+                .any(|(name, cells)| name == "karl" && cells.iter().any(|cell| cell.y == 1))
+        );
+
+        // the tick engine executes the plans as they are
+        assert!(world.load_schedule(&schedule).is_ok());
+        for tick in 1..=schedule.makespan() as u64 {
+            let events = world.step(tick);
+            assert!(
+                !events
+                    .iter()
+                    .any(|event| matches!(event, crate::simulation::WorldEvent::CommandFailed { .. }))
+            );
+        }
+        assert_eq!(world.get_robot_position("rusty"), Some(Position::new(4, 0)));
+        assert_eq!(world.get_robot_position("karl"), Some(Position::new(0, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn crowded_crossing_is_collision_free() -> Result<(), MultiPlanError> {
+        let mut world = World::new(5, 5);
+        let mut tasks = Vec::new();
+        // eight robots on the border, each heading to the opposite side
+        for (index, (x, y)) in [(0, 1), (0, 2), (0, 3), (4, 1), (4, 2), (4, 3), (2, 0), (2, 4)]
+            .into_iter()
+            .enumerate()
+        {
+            let name = format!("robot{index}");
+            world.add_robot_new(name.clone());
+            if let Some(robot) = world.get_robot_mut(&name) {
+                robot.position = Position::new(x, y);
+            }
+            tasks.push(task(&name, 4 - x, 4 - y));
+        }
+        let schedule = MultiPlanner::default().plan(&world, &tasks)?;
+        assert_eq!(schedule.plans.len(), 8);
+        assert!(schedule.is_collision_free());
+        for (task, (name, cells)) in tasks.iter().zip(&schedule.plans) {
+            assert_eq!(task.name, *name);
+            assert_eq!(cells.last(), Some(&task.goal));
+        }
+        assert!(schedule.sum_of_costs() >= 8 * 4);
+        Ok(())
+    }
+
+    #[test]
+    fn impossible_tasks_are_reported() {
+        let world = corridor();
+        let planner = MultiPlanner::default();
+        assert_eq!(
+            planner.plan(&world, &[task("rusty", 2, 1), task("karl", 2, 1)]),
+            Err(MultiPlanError::SharedGoal(Position::new(2, 1)))
+        );
+        assert_eq!(
+            planner.plan(&world, &[task("casper", 2, 1)]),
+            Err(MultiPlanError::UnknownRobot("casper".to_string()))
+        );
+        assert!(matches!(
+            planner.plan(&world, &[task("rusty", 1, 1)]),
+            Err(MultiPlanError::Plan {
+                error: PlanError::Blocked(_),
+                ..
+            })
+        ));
+        // karl parks in the middle of the corridor, rusty cannot get past him
+        assert_eq!(
+            planner.plan(&world, &[task("rusty", 4, 0), task("karl", 2, 0)]),
+            Err(MultiPlanError::NoSolution)
+        );
+    }
+}
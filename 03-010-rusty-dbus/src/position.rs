@@ -3,13 +3,17 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Hash, Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+/// A cell of the world grid, `y` grows to the north.
+#[derive(Hash, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct Position {
+    /// Column, `0` is the western border.
     pub x: i32,
+    /// Row, `0` is the southern border.
     pub y: i32,
 }
 
 impl Position {
+    /// Creates the position `(x/y)`.
     pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
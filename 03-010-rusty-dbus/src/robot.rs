@@ -4,76 +4,182 @@
 //! that can move in four directions with boundary enforcement.
 
 use crate::{
+    command::Command,
+    coverage::Visits,
+    explore::Exploration,
+    heading::Heading,
+    mapping::OccupancyGrid,
     moveable::{Direction, Moveable, MovementError},
     position::Position,
+    sensor::Lidar,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt;
+use std::path::PathBuf;
+
+/// State of charge of a fully charged battery.
+pub const FULL_CHARGE: u8 = u8::MAX;
+
+/// Energy consumed for every cell a robot moves.
+pub const ENERGY_PER_STEP: u8 = 1;
+
+/// Energy a robot gains per tick while standing on a charge pad.
+pub const CHARGE_PER_TICK: u8 = 5;
+
+fn full_charge() -> u8 {
+    FULL_CHARGE
+}
 
 /// A simple movable robot with a name and 2D position.
 ///
-/// The robot starts at coordinates (0, 0) and implements
-/// the [`Moveable`] trait to handle directional changes.
+/// The robot starts at coordinates (0, 0) facing [`Heading::North`] and
+/// implements the [`Moveable`] trait to handle directional changes.
 ///
 /// # Example
-/// ```rust
-/// use robot_control::moveable::{Direction, Moveable};
-/// use robot_control::robot::Robot;
+/// ```ignore
+/// use rusty_the_robot::moveable::{Direction, Moveable};
+/// use rusty_the_robot::robot::Robot;
 ///
 /// let mut bot = Robot::new("Rusty".to_string());
 /// bot.move_robot(Direction::Forward { step: 2 }).unwrap();
-/// assert_eq!(format!("{}", bot), "(Robot name: Rusty Position: 0, 2)");
+/// assert_eq!(format!("{}", bot), "(Robot name: Rusty Position: ((0/2)) Heading: N)");
 /// ```
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Robot {
     pub name: String,
     pub position: Position,
+    #[serde(default)]
+    pub heading: Heading,
+    /// Battery level, `255` is full.
+    #[serde(default = "full_charge")]
+    pub state_of_charge: u8,
+    /// Ghost robots may share cells with other robots (e.g. for debugging).
+    #[serde(default)]
+    pub ghost: bool,
+    /// Pending commands, the front one is executed next tick.
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub commands: VecDeque<Command>,
+    /// Rhai script deciding the next commands whenever the queue runs empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<PathBuf>,
+    /// Range sensor used by `Scan` and controller scripts.
+    #[serde(default)]
+    pub lidar: Lidar,
+    /// What the robot has observed of the world so far, built up by [`crate::world::World::step`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub belief: Option<OccupancyGrid>,
+    /// Set while the robot explores on its own, see [`crate::explore`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exploration: Option<Exploration>,
+    /// Cells the robot has entered, to rate how well it covers the world.
+    #[serde(default, skip_serializing_if = "Visits::is_empty")]
+    pub visits: Visits,
 }
 
 impl Robot {
     /// Creates a new [`Robot`] with the given name at position `(0, 0)`.
     ///
     /// # Example
-    /// ```rust
+    /// ```ignore
     /// let robot = Robot::new("robotname".to_string());
     /// ```
     pub fn new(name: String) -> Self {
         Robot {
             name,
             position: Position { x: 0, y: 0 },
+            heading: Heading::default(),
+            state_of_charge: FULL_CHARGE,
+            ghost: false,
+            commands: VecDeque::new(),
+            controller: None,
+            lidar: Lidar::default(),
+            belief: None,
+            exploration: None,
+            visits: Visits::default(),
         }
     }
 }
 
 impl fmt::Display for Robot {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(Robot name: {} Position: {})", self.name, self.position)
+        write!(
+            f,
+            "(Robot name: {} Position: {} Heading: {})",
+            self.name, self.position, self.heading
+        )
+    }
+}
+
+impl Robot {
+    /// Returns every cell the robot would pass through for `direction`,
+    /// in order, without actually moving it.
+    ///
+    /// Moves are relative to the current [`Heading`]; turns pass through
+    /// no cells and yield an empty trajectory. The last entry is the destination. The robot's own limits
+    /// (maximum step count, no negative steps) are enforced here so
+    /// that callers like the world can check each cell before committing.
+    ///
+    /// # Errors
+    /// Returns [`MovementError::TooFar`] or [`MovementError::InvalidStep`]
+    /// if the movement exceeds limits.
+    pub fn trajectory(&self, direction: &Direction) -> Result<Vec<Position>, MovementError> {
+        let (heading, steps) = match *direction {
+            Direction::Forward { step } => {
+                if step > 3 {
+                    return Err(MovementError::TooFar);
+                }
+                if step < 0 {
+                    return Err(MovementError::InvalidStep);
+                }
+                (self.heading, step)
+            }
+            Direction::Backwards => (self.heading.turn_left().turn_left(), 1),
+            Direction::Left => (self.heading.turn_left(), 1),
+            Direction::Right => (self.heading.turn_right(), 1),
+            Direction::TurnLeft | Direction::TurnRight => (self.heading, 0),
+        };
+        let (dx, dy) = heading.delta();
+        Ok((1..=steps)
+            .map(|i| Position::new(self.position.x + dx * i, self.position.y + dy * i))
+            .collect())
+    }
+
+    /// Applies the heading change of a turn command; other directions are ignored.
+    pub fn turn(&mut self, direction: &Direction) {
+        match direction {
+            Direction::TurnLeft => self.heading = self.heading.turn_left(),
+            Direction::TurnRight => self.heading = self.heading.turn_right(),
+            _ => {}
+        }
     }
 }
 
 impl Moveable for Robot {
     /// Moves the robot in the specified [`Direction`].
     ///
+    /// Each cell costs [`ENERGY_PER_STEP`] of the battery.
+    ///
     /// # Errors
-    /// Returns [`MovementError::TooFar`] if the movement exceeds limits.
+    /// Returns [`MovementError::TooFar`] if the movement exceeds limits and
+    /// [`MovementError::BatteryEmpty`] if the battery does not last for it.
     ///
     /// # Example
-    /// ```rust
+    /// ```ignore
     /// let mut robot = Robot::new("robotname".to_string());
     /// robot.move_robot(Direction::Forward { step:3 })?;
     /// ```
     fn move_robot(&mut self, direction: Direction) -> Result<(), MovementError> {
-        match direction {
-            Direction::Forward { step } => {
-                if step > 3 {
-                    return Err(MovementError::TooFar);
-                }
-                self.position.y += step;
-            }
-            Direction::Backwards => self.position.y -= 1,
-            Direction::Left => self.position.x -= 1,
-            Direction::Right => self.position.x += 1,
+        let mut trajectory = self.trajectory(&direction)?;
+        let cost = u32::from(ENERGY_PER_STEP) * trajectory.len() as u32;
+        if cost > u32::from(self.state_of_charge) {
+            return Err(MovementError::BatteryEmpty);
+        }
+        if let Some(target) = trajectory.pop() {
+            self.position = target;
+            self.state_of_charge -= cost as u8;
         }
+        self.turn(&direction);
         Ok(())
     }
 }
@@ -145,4 +251,40 @@ mod tests {
         // assert the not changed values
         assert_eq!(robot.position, Position { x: 0, y: 0 });
     }
+
+    #[test]
+    fn move_robot_along_heading() {
+        init();
+        let mut robot = Robot::new("rusty-test".to_string());
+        assert!(robot.move_robot(Direction::TurnRight).is_ok());
+        assert_eq!(robot.heading, Heading::East);
+        assert!(robot.move_robot(Direction::Forward { step: 2 }).is_ok());
+        assert_eq!(robot.position, Position { x: 2, y: 0 });
+        // strafe left while facing east means moving north
+        assert!(robot.move_robot(Direction::Left).is_ok());
+        assert_eq!(robot.position, Position { x: 2, y: 1 });
+        assert!(robot.move_robot(Direction::Backwards).is_ok());
+        assert_eq!(robot.position, Position { x: 1, y: 1 });
+        assert_eq!(
+            format!("{robot}"),
+            "(Robot name: rusty-test Position: ((1/1)) Heading: E)"
+        );
+    }
+
+    #[test]
+    fn move_robot_drains_battery() {
+        init();
+        let mut robot = Robot::new("rusty-test".to_string());
+        robot.state_of_charge = 3;
+        assert!(robot.move_robot(Direction::Forward { step: 2 }).is_ok());
+        assert_eq!(robot.state_of_charge, 1);
+        assert_eq!(
+            robot.move_robot(Direction::Forward { step: 2 }),
+            Err(MovementError::BatteryEmpty)
+        );
+        assert_eq!(robot.position, Position { x: 0, y: 2 });
+        // turning is free
+        assert!(robot.move_robot(Direction::TurnLeft).is_ok());
+        assert_eq!(robot.state_of_charge, 1);
+    }
 }
@@ -7,25 +7,42 @@
 //!
 //! # Example
 //! ```no_run
-//! use robot_control::run::run;
+//! use rusty_the_robot::run::run;
 //!
-//! fn main() {
-//!     run(); // prints robot movements and errors
+//! #[tokio::main]
+//! async fn main() -> std::process::ExitCode {
+//!     run().await // prints robot movements and errors
 //! }
 //! ```
 
 // note: You need to use the Moveable trait here, otherwise no access to the trait fn
-use crate::cli::Cli;
+use crate::cli::{Action, Cli};
+use crate::command::Program;
+use crate::dsl;
+use crate::generate::{self, Layout};
 use crate::moveable::{Direction, Moveable};
 use crate::position::Position;
 use crate::robot::Robot;
-use crate::world::dbus::WorldDbus;
+use crate::simulation::{Simulation, WorldEvent};
+use crate::world::dbus::{WorldDbus, export_robots, forward_events};
+use crate::world::load::WorldLoadError;
+use crate::world::migration::{self, FORMAT_VERSION};
+use crate::world::storage::Format;
 use crate::world::{Tile, World};
 use clap::Parser;
 use env_logger::{self, Env};
 use log::{debug, error, info, trace, warn};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::Notify;
+use zbus::fdo::ObjectManager;
+
+/// Well-known bus name of the service.
+const BUS_NAME: &str = "de.marc.rusty";
 
 /// Runs a sample sequence of robot movements.
 ///
@@ -33,12 +50,30 @@ use tokio::sync::Mutex;
 /// It constructs a new [`Robot`], executes a few movement commands,
 /// and prints results to the console.
 ///
-/// # Panics
-/// Panics if any `unwrap()` operation fails (used for demonstration purposes only).
-pub async fn run() {
+/// Afterwards the world is served on D-Bus until SIGINT, SIGTERM or a
+/// `Quit` call arrives. On shutdown the bus name is released and the world
+/// is saved to the configured path.
+///
+/// Returns [`ExitCode::FAILURE`] if D-Bus setup or saving the world fails;
+/// the reason is logged.
+pub async fn run() -> ExitCode {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
     let cli = Cli::parse();
-    let mut robot = Robot::new(cli.name); // changed to mut to use move_robot
+    if !cli.migrate.is_empty() {
+        return migrate(&cli.migrate, cli.format);
+    }
+    match &cli.action {
+        Some(Action::Check { file }) => return check(file, Format::choose(cli.format, file)),
+        Some(Action::Convert {
+            input,
+            output,
+            from,
+            to,
+        }) => return convert(input, output, *from, *to),
+        _ => {}
+    }
+    let format = Format::choose(cli.format, &cli.world);
+    let mut robot = Robot::new(cli.name.clone()); // changed to mut to use move_robot
     info!("Display output {robot}");
     info!("Debug output {robot:?}\n");
 
@@ -62,42 +97,416 @@ pub async fn run() {
     let _ = robot.move_robot(Direction::Right);
     let _ = robot.move_robot(Direction::Right);
     info!("{robot}");
+    let _ = robot.move_robot(Direction::TurnLeft);
+    let _ = robot.move_robot(Direction::TurnLeft);
+    let _ = robot.move_robot(Direction::TurnRight);
+    info!("{robot}");
 
-    let path = "world.json";
-    let world_opt: Option<World> = std::fs::read_to_string(path)
-        .ok()
-        .and_then(|data| serde_json::from_str(&data).ok());
+    // also with --map and --generate, whose world replaces the file on shutdown
+    let loaded = match World::load(&cli.world, format) {
+        Ok(world) => Some(world),
+        Err(WorldLoadError::Io(e)) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) if cli.force_new => {
+            warn!("Could not load {}: {e}", cli.world.display());
+            None
+        }
+        Err(e) => {
+            error!(
+                "Could not load {}: {e}, use --force-new to replace it with a new world",
+                cli.world.display()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let world_opt: Option<World> = match (&cli.map, cli.generate) {
+        (Some(_), _) | (_, Some(_)) => None,
+        (None, None) => loaded,
+    };
 
-    let mut world = match world_opt {
-        Some(world) => {
-            info!("Loaded world from json");
+    let mut world = match (world_opt, &cli.map, cli.generate) {
+        (Some(world), ..) => {
+            info!("Loaded world from {}", cli.world.display());
             world
         }
-        None => {
+        (None, Some(path), _) => match load_map(path) {
+            Ok(world) => {
+                info!("Loaded map {}", path.display());
+                world
+            }
+            Err(e) => {
+                error!("Could not load map {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None, Some(layout)) => match generate_world(layout, &cli, robot) {
+            Ok(world) => world,
+            Err(e) => {
+                error!("Could not generate a {layout:?} world: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None, None) => {
             warn!("Created new world");
             let mut world = World::new(cli.height, cli.width);
             world.add_tile(Position { x: 0, y: 0 }, Tile::Wall);
             world.add_robot_existing(robot);
-            world.add_robot_new(String::from("karl"));
+            // next to the wall, not inside it
+            let mut karl = Robot::new(String::from("karl"));
+            karl.position = Position { x: 0, y: 1 };
+            world.add_robot_existing(karl);
             world
         }
     };
-    let _ = world.move_robot("karl", Direction::Forward { step: 2 });
-
-    let world = Arc::new(Mutex::new(world));
-    let world_iface = WorldDbus::new(world.clone());
-    let connection = zbus::Connection::session().await.unwrap();
-    connection
-        .object_server()
-        .at("/", world_iface)
-        .await
-        .unwrap();
-    connection.request_name("de.marc.rusty").await.unwrap();
-
-    loop {}
-
-    info!("{world:?}");
-    let world = world.lock().await;
-    let data = serde_json::to_string_pretty(&*world).unwrap();
-    std::fs::write(path, data).unwrap();
+    if cli.ghost {
+        let _ = world
+            .set_ghost(&cli.name, true)
+            .inspect_err(|e| error!("Could not make {} a ghost: {e:?}", cli.name));
+    }
+    if let Some(policy) = cli.move_policy {
+        world.set_move_policy(policy);
+    }
+    let _ = world
+        .move_robot("karl", Direction::Forward { step: 2 })
+        .inspect_err(|e| error!("Movement Error {e:?}"));
+    if let Some(path) = &cli.program {
+        let loaded = Program::load(path)
+            .map_err(|e| format!("{e:?}"))
+            .and_then(|program| {
+                world
+                    .load_program(&cli.name, program)
+                    .map_err(|e| format!("{e:?}"))
+            });
+        match loaded {
+            Ok(()) => info!("Queued program {} for {}", path.display(), cli.name),
+            Err(e) => {
+                error!("Could not load program {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(path) = &cli.controller {
+        match world.get_robot_mut(&cli.name) {
+            Some(robot) => robot.controller = Some(path.clone()),
+            None => {
+                error!("Could not attach controller, robot {} not found", cli.name);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if cli.explore
+        && let Err(e) = world.start_exploration(&cli.name)
+    {
+        error!("Could not start exploring: {e:?}");
+        return ExitCode::FAILURE;
+    }
+    if cli.cover {
+        match world.cover(&cli.name) {
+            Ok(report) => info!(
+                "Planned route for {} covers {:.1}% with {:.2} revisits per cell",
+                cli.name,
+                100.0 * report.ratio(),
+                report.overhead()
+            ),
+            Err(e) => {
+                error!("Could not plan a coverage route: {e:?}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    match &cli.action {
+        Some(Action::RunProgram { file }) => return run_program(file, &cli, world),
+        Some(Action::ExportMap { file }) => return export_map(file, &world),
+        Some(Action::Check { .. } | Action::Convert { .. }) | None => {}
+    }
+
+    let mut simulation = Simulation::new(world);
+    simulation.set_script_budget(cli.script_budget);
+    let world = simulation.world();
+    if let Some(ticks) = cli.ticks {
+        info!("Running {ticks} ticks without D-Bus");
+        for event in simulation.step_n(ticks).await {
+            debug!("{event:?}");
+            for change in &event.events {
+                match change {
+                    WorldEvent::ExplorationProgress { name, coverage } => {
+                        info!("{name} explored {coverage:.1}% of the world");
+                    }
+                    WorldEvent::ExplorationFinished { name, coverage } => {
+                        info!("{name} finished exploring with {coverage:.1}% coverage");
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let world = world.lock().await;
+        if cli.cover
+            && let Some(robot) = world.get_robot(&cli.name)
+        {
+            let report = robot.visits.report(&world);
+            info!(
+                "{} covered {:.1}% with {:.2} revisits per cell",
+                cli.name,
+                100.0 * report.ratio(),
+                report.overhead()
+            );
+        }
+        return save_on_exit(&cli.world, format, &world);
+    }
+
+    let shutdown = Arc::new(Notify::new());
+    let world_iface = WorldDbus::new(world.clone(), simulation.events(), shutdown.clone());
+    let connection = match zbus::Connection::session().await {
+        Ok(connection) => connection,
+        Err(e) => {
+            error!("Could not connect to the session bus: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let server = connection.object_server();
+    let served = async {
+        server.at("/", ObjectManager).await?;
+        server.at("/", world_iface).await?;
+        export_robots(server, world.clone()).await
+    };
+    if let Err(e) = served.await {
+        error!("Could not serve the world on D-Bus: {e}");
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = connection.request_name(BUS_NAME).await {
+        error!("Could not request the bus name: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let forwarder = tokio::spawn(forward_events(
+        connection.clone(),
+        world.clone(),
+        simulation.subscribe(),
+    ));
+    let engine = tokio::spawn(simulation.run(Duration::from_millis(cli.tick_ms)));
+
+    if let Err(e) = wait_for_shutdown(&shutdown).await {
+        error!("Could not listen for signals: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    engine.abort();
+    forwarder.abort();
+    if let Err(e) = connection.release_name(BUS_NAME).await {
+        warn!("Could not release the bus name: {e}");
+    }
+    // lets pending replies (like the one to `Quit`) go out before we exit
+    connection.graceful_shutdown().await;
+    save_on_exit(&cli.world, format, &*world.lock().await)
+}
+
+/// Generates a world of `layout` as configured in `cli` and puts `robot`
+/// and karl on the first cells they can stand on, see [`World::free_cell`].
+///
+/// Fails if the result does not pass [`World::validate`], e.g. because the
+/// world has no room for `robot`.
+fn generate_world(layout: Layout, cli: &Cli, mut robot: Robot) -> Result<World, String> {
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    info!(
+        "Generated {layout:?} world with seed {seed}, {} was not loaded",
+        cli.world.display()
+    );
+    let mut world = generate::generate(layout, cli.width, cli.height, seed);
+    generate::scatter_charge_pads(&mut world, cli.charge_pads, seed);
+    robot.position = world.free_cell().unwrap_or_default();
+    world.add_robot_existing(robot);
+    if let Some(position) = world.free_cell() {
+        let mut karl = Robot::new(String::from("karl"));
+        karl.position = position;
+        world.add_robot_existing(karl);
+    }
+    let diagnostics = world.validate();
+    if !diagnostics.is_empty() {
+        return Err(WorldLoadError::Validation(diagnostics).to_string());
+    }
+    Ok(world)
+}
+
+/// Prints what is wrong with the world file at `path`.
+///
+/// Fails if the file cannot be loaded or [`World::validate`] finds problems.
+fn check(path: &Path, format: Format) -> ExitCode {
+    let problems = match World::load(path, format) {
+        Ok(_) => Vec::new(),
+        Err(WorldLoadError::Validation(diagnostics)) => {
+            diagnostics.iter().map(|d| d.to_string()).collect()
+        }
+        Err(e) => vec![e.to_string()],
+    };
+    for problem in &problems {
+        println!("{}: {problem}", path.display());
+    }
+    if problems.is_empty() {
+        println!("{}: no problems found", path.display());
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Upgrades the world files at `paths` in place to [`FORMAT_VERSION`].
+///
+/// Every file is tried; fails if any of them could not be upgraded.
+fn migrate(paths: &[PathBuf], format: Option<Format>) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    for path in paths {
+        match migrate_file(path, Format::choose(format, path)) {
+            Ok(version) if version == FORMAT_VERSION => {
+                info!("{} is up to date", path.display());
+            }
+            Ok(version) => info!(
+                "Upgraded {} from format version {version} to {FORMAT_VERSION}",
+                path.display()
+            ),
+            Err(e) => {
+                error!("Could not upgrade {}: {e}", path.display());
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Upgrades the world file at `path`, returns the version it had.
+///
+/// Problems [`World::validate`] finds are logged but do not stop the
+/// upgrade, they are fixed more easily in the current format.
+fn migrate_file(path: &Path, format: Format) -> Result<u64, WorldLoadError> {
+    let mut value = format.parse(&std::fs::read(path)?)?;
+    let version = migration::upgrade(&mut value)?;
+    if version < FORMAT_VERSION {
+        let world = migration::from_value(value)?;
+        for diagnostic in world.validate() {
+            warn!("{}: {diagnostic}", path.display());
+        }
+        world.save(path, format)?;
+    }
+    Ok(version)
+}
+
+/// Writes the world file `input` to `output` in another format.
+///
+/// Worlds with problems are converted all the same, so they can be fixed
+/// in a format that is easier to edit; the problems are logged.
+fn convert(input: &Path, output: &Path, from: Option<Format>, to: Option<Format>) -> ExitCode {
+    let (from, to) = (Format::choose(from, input), Format::choose(to, output));
+    let converted = std::fs::read(input)
+        .map_err(WorldLoadError::from)
+        .and_then(|data| Ok(World::decode(&data, from)?))
+        .and_then(|world| {
+            for diagnostic in world.validate() {
+                warn!("{}: {diagnostic}", input.display());
+            }
+            Ok(world.save(output, to)?)
+        });
+    match converted {
+        Ok(()) => {
+            info!(
+                "Converted {} ({from:?}) to {} ({to:?})",
+                input.display(),
+                output.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Could not convert {}: {e}", input.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads the text map at `path`, see [`World::from_ascii`].
+fn load_map(path: &Path) -> Result<World, String> {
+    let map = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let world = World::from_ascii(&map).map_err(|e| e.to_string())?;
+    let diagnostics = world.validate();
+    if !diagnostics.is_empty() {
+        return Err(WorldLoadError::Validation(diagnostics).to_string());
+    }
+    Ok(world)
+}
+
+/// Writes `world` as text map to `file` and maps the outcome to the exit code.
+fn export_map(file: &Path, world: &World) -> ExitCode {
+    match std::fs::write(file, world.to_ascii()) {
+        Ok(()) => {
+            info!("Wrote map to {}", file.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Could not write the map to {}: {e}", file.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the robot program in `file` on robot `cli.name` and saves the world.
+///
+/// Parse and runtime errors are logged with the offending source line;
+/// after a runtime error the moves done so far are kept and saved.
+fn run_program(file: &Path, cli: &Cli, mut world: World) -> ExitCode {
+    let source = match std::fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Could not read {}: {e}", file.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let name = file.display().to_string();
+    let script = match dsl::parse(&source) {
+        Ok(script) => script,
+        Err(e) => {
+            error!("{}", e.report(&name, &source));
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = dsl::run(&script, &mut world, &cli.name);
+    if let Some(robot) = world.get_robot(&cli.name) {
+        info!("{robot}");
+    }
+    let saved = save_on_exit(&cli.world, Format::choose(cli.format, &cli.world), &world);
+    match result {
+        Ok(steps) => {
+            info!("Program {name} finished after {steps} steps");
+            saved
+        }
+        Err(e) => {
+            error!("{}", e.report(&name, &source));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Saves `world` to `path` in `format` and maps the outcome to the exit code.
+fn save_on_exit(path: &Path, format: Format, world: &World) -> ExitCode {
+    debug!("{world:?}");
+    match world.save(path, format) {
+        Ok(()) => {
+            info!("Saved world to {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Could not save the world to {}: {e}", path.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Waits until SIGINT, SIGTERM or a D-Bus `Quit` call asks the service to stop.
+async fn wait_for_shutdown(quit: &Notify) -> std::io::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            info!("Received SIGINT, shutting down");
+        }
+        _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+        _ = quit.notified() => info!("Quit requested over D-Bus, shutting down"),
+    }
+    Ok(())
 }
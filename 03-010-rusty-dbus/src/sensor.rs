@@ -0,0 +1,267 @@
+//! Simulated range sensors.
+//!
+//! A [`crate::sensor::Lidar`] casts rays from the center of a robot's cell
+//! across the tile grid and reports for every beam how far away the nearest
+//! wall or robot is. The world border and closed doors count as walls, ghost
+//! robots are invisible.
+
+use crate::heading::Heading;
+use crate::moveable::MovementError;
+use crate::position::Position;
+use crate::world::World;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Most beams a lidar may have.
+pub const MAX_BEAMS: u32 = 360;
+
+/// Farthest a lidar may see, in cells.
+pub const MAX_RANGE: f64 = 100.0;
+
+/// A rotating range sensor mounted on a robot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lidar {
+    /// Number of beams, spread evenly around the robot starting straight ahead.
+    pub beams: u32,
+    /// Maximum distance in cells a beam can measure.
+    pub range: f64,
+}
+
+impl Default for Lidar {
+    fn default() -> Self {
+        Self {
+            beams: 8,
+            range: 8.0,
+        }
+    }
+}
+
+/// What a beam ran into.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hit {
+    /// A wall, closed door or the world border.
+    Wall,
+    /// The named robot.
+    Robot(String),
+    /// Nothing within range.
+    Nothing,
+}
+
+impl Hit {
+    /// Short name of what was hit: `wall`, `robot` or `nothing`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Hit::Wall => "wall",
+            Hit::Robot(_) => "robot",
+            Hit::Nothing => "nothing",
+        }
+    }
+}
+
+/// Measurement of a single beam.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// Direction of the beam in degrees, counter-clockwise from the robot's heading.
+    pub angle: f64,
+    /// Distance in cells from the robot's center to the hit, `range` for [`Hit::Nothing`].
+    pub distance: f64,
+    pub hit: Hit,
+    /// Cell the beam ended in, `None` for [`Hit::Nothing`].
+    ///
+    /// Needed where a beam passes a corner: both cells there are entered at the same distance.
+    pub cell: Option<Position>,
+}
+
+/// Absolute direction in radians, counter-clockwise from east, of a beam
+/// `angle` degrees left of `heading`.
+pub fn beam_direction(heading: Heading, angle: f64) -> f64 {
+    let (dx, dy) = heading.delta();
+    f64::from(dy).atan2(f64::from(dx)) + angle * PI / 180.0
+}
+
+/// The cells a ray from the center of a cell crosses, in order.
+///
+/// Yields every cell together with the distance at which the ray enters it.
+/// The ray never ends, callers stop at the range they are interested in.
+pub struct Ray {
+    cell: Position,
+    step_x: i32,
+    step_y: i32,
+    /// distance along the ray between two vertical or horizontal cell borders
+    delta_x: f64,
+    delta_y: f64,
+    /// distance to the next vertical or horizontal cell border
+    next_x: f64,
+    next_y: f64,
+}
+
+impl Ray {
+    /// Starts in the center of `start` in `direction`, see [`beam_direction`].
+    pub fn new(start: &Position, direction: f64) -> Self {
+        let (dx, dy) = (direction.cos(), direction.sin());
+        let delta_x = 1.0 / dx.abs();
+        let delta_y = 1.0 / dy.abs();
+        Self {
+            cell: start.clone(),
+            step_x: if dx > 0.0 { 1 } else { -1 },
+            step_y: if dy > 0.0 { 1 } else { -1 },
+            delta_x,
+            delta_y,
+            next_x: 0.5 * delta_x,
+            next_y: 0.5 * delta_y,
+        }
+    }
+}
+
+impl Iterator for Ray {
+    type Item = (Position, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let distance = if self.next_x < self.next_y {
+            self.cell.x += self.step_x;
+            self.next_x += self.delta_x;
+            self.next_x - self.delta_x
+        } else {
+            self.cell.y += self.step_y;
+            self.next_y += self.delta_y;
+            self.next_y - self.delta_y
+        };
+        Some((self.cell.clone(), distance))
+    }
+}
+
+impl Lidar {
+    /// Whether the configuration is within [`MAX_BEAMS`] and [`MAX_RANGE`].
+    pub fn is_valid(&self) -> bool {
+        (1..=MAX_BEAMS).contains(&self.beams) && self.range > 0.0 && self.range <= MAX_RANGE
+    }
+
+    /// Scans the surroundings of robot `name`, one [`Reading`] per beam.
+    ///
+    /// # Errors
+    /// Returns [`MovementError::UnknownRobot`] if there is no such robot.
+    pub fn scan(&self, world: &World, name: &str) -> Result<Vec<Reading>, MovementError> {
+        let robot = world
+            .get_robot(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        Ok((0..self.beams)
+            .map(|beam| {
+                let angle = 360.0 * f64::from(beam) / f64::from(self.beams);
+                let direction = beam_direction(robot.heading, angle);
+                let (distance, hit, cell) = self.cast(world, name, &robot.position, direction);
+                Reading {
+                    angle,
+                    distance,
+                    hit,
+                    cell,
+                }
+            })
+            .collect())
+    }
+
+    /// Follows a ray from `start` in `direction` until it hits something.
+    fn cast(
+        &self,
+        world: &World,
+        name: &str,
+        start: &Position,
+        direction: f64,
+    ) -> (f64, Hit, Option<Position>) {
+        for (cell, distance) in Ray::new(start, direction) {
+            if distance > self.range {
+                break;
+            }
+            if !world.in_bounds(&cell) || world.is_blocked(&cell) {
+                return (distance, Hit::Wall, Some(cell));
+            }
+            if let Some(other) = world.occupant(&cell).filter(|other| other.name != name) {
+                return (distance, Hit::Robot(other.name.clone()), Some(cell));
+            }
+        }
+        (self.range, Hit::Nothing, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moveable::Direction;
+    use crate::world::Tile;
+
+    /// Empty 10x10 world with `rusty` at (2/2) facing north.
+    fn world() -> World {
+        let mut world = World::new(10, 10);
+        world.add_robot_new(String::from("rusty"));
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            robot.position = Position::new(2, 2);
+        }
+        world
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn scan_measures_walls_and_border() -> Result<(), MovementError> {
+        let mut world = world();
+        world.add_tile(Position::new(2, 5), Tile::Wall);
+        let lidar = Lidar {
+            beams: 4,
+            range: 20.0,
+        };
+        let readings = lidar.scan(&world, "rusty")?;
+        let angles: Vec<f64> = readings.iter().map(|reading| reading.angle).collect();
+        assert_eq!(angles, vec![0.0, 90.0, 180.0, 270.0]);
+        // ahead (north) the wall, left (west) and behind (south) the border
+        assert!(close(readings[0].distance, 2.5));
+        assert!(close(readings[1].distance, 2.5));
+        assert!(close(readings[2].distance, 2.5));
+        // right (east) the border at x = 10
+        assert!(close(readings[3].distance, 7.5));
+        assert!(readings.iter().all(|reading| reading.hit == Hit::Wall));
+        assert_eq!(readings[0].cell, Some(Position::new(2, 5)));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_follows_heading_and_sees_robots() -> Result<(), MovementError> {
+        let mut world = world();
+        world.add_robot_new(String::from("karl"));
+        if let Some(karl) = world.get_robot_mut("karl") {
+            karl.position = Position::new(5, 5);
+        }
+        assert!(world.move_robot("rusty", Direction::TurnRight).is_ok());
+        let lidar = Lidar {
+            beams: 8,
+            range: 20.0,
+        };
+        let readings = lidar.scan(&world, "rusty")?;
+        // 45 degrees left of east is the diagonal towards karl
+        assert_eq!(readings[1].angle, 45.0);
+        assert_eq!(readings[1].hit, Hit::Robot(String::from("karl")));
+        assert!(close(readings[1].distance, 2.5 * 2f64.sqrt()));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_is_limited_by_range() -> Result<(), MovementError> {
+        let world = world();
+        let lidar = Lidar {
+            beams: 1,
+            range: 3.0,
+        };
+        assert_eq!(
+            lidar.scan(&world, "rusty")?,
+            vec![Reading {
+                angle: 0.0,
+                distance: 3.0,
+                hit: Hit::Nothing,
+                cell: None,
+            }]
+        );
+        assert!(lidar.scan(&world, "karl").is_err());
+        assert!(!Lidar { beams: 0, ..lidar }.is_valid());
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+//! Fixed-rate simulation engine for the [`crate::world::World`].
+//!
+//! The [`crate::simulation::Simulation`] owns the world and advances it in
+//! discrete ticks. Every tick first lets the controller scripts of idle robots
+//! queue new commands, see [`crate::controller`], then applies queued robot
+//! commands, recharges robots on charge pads and updates dynamic tiles, see
+//! [`crate::world::World::step`].
+//! What happened is published as a [`crate::simulation::TickEvent`] to all
+//! subscribers, e.g. the D-Bus layer.
+
+use crate::controller::Controllers;
+use crate::heading::Heading;
+use crate::moveable::MovementError;
+use crate::position::Position;
+use crate::world::{Tile, World};
+use log::trace;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, broadcast};
+use tokio::time::MissedTickBehavior;
+
+/// Number of tick events buffered for slow subscribers.
+const EVENT_CAPACITY: usize = 64;
+
+/// A single change caused by a tick.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldEvent {
+    /// A robot executed a command and now stands at `position`.
+    RobotMoved {
+        name: String,
+        position: Position,
+        heading: Heading,
+    },
+    /// A robot's battery was recharged.
+    RobotCharged { name: String, state_of_charge: u8 },
+    /// A queued command could not be executed and was dropped.
+    CommandFailed { name: String, error: MovementError },
+    /// A dynamic tile changed its state.
+    TileChanged { position: Position, tile: Tile },
+    /// The controller script of a robot did not compile, failed or ran out of budget.
+    ScriptFailed { name: String, error: String },
+    /// An exploring robot knows more of the map now, `coverage` in percent.
+    ExplorationProgress { name: String, coverage: f64 },
+    /// A robot stopped exploring, nothing reachable is left unknown.
+    ExplorationFinished { name: String, coverage: f64 },
+}
+
+/// Everything that happened during one tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickEvent {
+    /// Number of the tick, starting at 1.
+    pub tick: u64,
+    pub events: Vec<WorldEvent>,
+}
+
+/// Drives a [`World`] in discrete ticks.
+pub struct Simulation {
+    world: Arc<Mutex<World>>,
+    tick: u64,
+    events: broadcast::Sender<TickEvent>,
+    controllers: Controllers,
+}
+
+impl Simulation {
+    /// Takes ownership of `world`, starting at tick 0.
+    pub fn new(world: World) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            world: Arc::new(Mutex::new(world)),
+            tick: 0,
+            events,
+            controllers: Controllers::default(),
+        }
+    }
+
+    /// Limits every controller script to `budget` operations per tick.
+    pub fn set_script_budget(&mut self, budget: u64) {
+        self.controllers = Controllers::new(budget);
+    }
+
+    /// Shared handle for reading and changing the world between ticks.
+    pub fn world(&self) -> Arc<Mutex<World>> {
+        self.world.clone()
+    }
+
+    /// Sender side of the event stream, to subscribe later on.
+    pub fn events(&self) -> broadcast::Sender<TickEvent> {
+        self.events.clone()
+    }
+
+    /// Subscribes to the events of all following ticks.
+    pub fn subscribe(&self) -> broadcast::Receiver<TickEvent> {
+        self.events.subscribe()
+    }
+
+    /// Advances the world by exactly one tick and publishes what happened.
+    pub async fn step(&mut self) -> TickEvent {
+        self.tick += 1;
+        let mut world = self.world.lock().await;
+        let mut events = self.controllers.run(&mut world, self.tick);
+        events.extend(world.step(self.tick));
+        drop(world);
+        let event = TickEvent {
+            tick: self.tick,
+            events,
+        };
+        trace!("{event:?}");
+        // no subscribers is fine
+        let _ = self.events.send(event.clone());
+        event
+    }
+
+    /// Advances the world by `ticks` ticks as fast as possible.
+    ///
+    /// Deterministic counterpart to [`Simulation::run`], meant for tests.
+    pub async fn step_n(&mut self, ticks: u64) -> Vec<TickEvent> {
+        let mut events = Vec::new();
+        for _ in 0..ticks {
+            events.push(self.step().await);
+        }
+        events
+    }
+
+    /// Advances the world forever, one tick every `rate`.
+    ///
+    /// Ticks that are missed because the world was locked too long are
+    /// skipped instead of being caught up in a burst.
+    pub async fn run(mut self, rate: Duration) {
+        let mut interval = tokio::time::interval(rate);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        interval.tick().await; // the first tick completes immediately
+        loop {
+            interval.tick().await;
+            self.step().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+    use crate::robot::CHARGE_PER_TICK;
+
+    fn simulation() -> Simulation {
+        let mut world = World::new(5, 5);
+        world.add_robot_new(String::from("rusty"));
+        Simulation::new(world)
+    }
+
+    #[tokio::test]
+    async fn step_applies_one_command_per_tick() {
+        let mut simulation = simulation();
+        let world = simulation.world();
+        for _ in 0..2 {
+            let queued = world
+                .lock()
+                .await
+                .queue_command("rusty", Command::Forward { step: 1 });
+            assert!(queued.is_ok());
+        }
+        let mut events = simulation.subscribe();
+
+        let first = simulation.step().await;
+        assert_eq!(first.tick, 1);
+        assert_eq!(
+            first.events,
+            vec![WorldEvent::RobotMoved {
+                name: String::from("rusty"),
+                position: Position::new(0, 1),
+                heading: Heading::North,
+            }]
+        );
+        assert_eq!(events.recv().await.ok(), Some(first));
+
+        simulation.step_n(3).await;
+        assert_eq!(
+            world.lock().await.get_robot_position("rusty"),
+            Some(Position::new(0, 2))
+        );
+    }
+
+    #[tokio::test]
+    async fn step_reports_failed_commands() {
+        let mut simulation = simulation();
+        let world = simulation.world();
+        assert!(
+            world
+                .lock()
+                .await
+                .queue_command("rusty", Command::Backwards)
+                .is_ok()
+        );
+        let event = simulation.step().await;
+        assert!(event.events.contains(&WorldEvent::CommandFailed {
+            name: String::from("rusty"),
+            error: MovementError::OutOfBounds,
+        }));
+        // a failed command is dropped
+        assert!(simulation.step().await.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn step_recharges_and_toggles_doors() {
+        let mut world = World::new(5, 5);
+        world.add_robot_new(String::from("rusty"));
+        world.add_tile(Position::new(0, 0), Tile::ChargePad);
+        world.add_tile(
+            Position::new(2, 2),
+            Tile::Door {
+                open: true,
+                period: 2,
+            },
+        );
+        if let Some(robot) = world.get_robot_mut("rusty") {
+            robot.state_of_charge = 0;
+        }
+        let mut simulation = Simulation::new(world);
+
+        let events = simulation.step_n(2).await;
+        assert_eq!(
+            events[0].events,
+            vec![WorldEvent::RobotCharged {
+                name: String::from("rusty"),
+                state_of_charge: CHARGE_PER_TICK,
+            }]
+        );
+        assert!(events[1].events.contains(&WorldEvent::TileChanged {
+            position: Position::new(2, 2),
+            tile: Tile::Door {
+                open: false,
+                period: 2
+            },
+        }));
+        assert!(
+            simulation
+                .world()
+                .lock()
+                .await
+                .is_blocked(&Position::new(2, 2))
+        );
+    }
+}
@@ -1,21 +1,58 @@
+pub mod ascii;
 pub mod dbus;
+pub mod load;
+pub mod migration;
+pub mod storage;
+pub mod validate;
 
 #[cfg(test)]
 mod tests;
 
-use crate::moveable::{Direction, Moveable};
-use crate::robot::Robot;
+use crate::command::{Command, Program};
+use crate::coverage::{self, CoverageReport, Visits};
+use crate::explore::{Decision, Exploration};
+use crate::mapping::OccupancyGrid;
+use crate::moveable::Direction;
+use crate::planner::PlanError;
+use crate::planner::multi::{self, Schedule};
+use crate::robot::{CHARGE_PER_TICK, ENERGY_PER_STEP, FULL_CHARGE, Robot};
+use crate::simulation::WorldEvent;
 use crate::{moveable::MovementError, position::Position};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a cell of the world holds, unset cells are empty.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Tile {
+    /// Nothing, like an unset cell.
     Empty,
+    /// Blocks robots and sensors.
     Wall,
+    /// Recharges a robot standing on it every tick.
+    ChargePad,
+    /// Opens and closes every `period` ticks, blocks like a wall while closed.
+    ///
+    /// A door never closes on a robot. A `period` of `0` keeps it as it is.
+    Door {
+        /// Whether robots can pass right now.
+        open: bool,
+        /// Ticks between opening and closing.
+        period: u32,
+    },
+}
+
+/// How [`World::move_robot`] reacts when a move hits a wall or the border.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum MovePolicy {
+    /// Leave the robot on the last legal cell before the obstacle.
+    #[default]
+    StopAtObstacle,
+    /// Reject the whole move and keep the robot where it was.
+    RejectWhole,
 }
 
+/// The tile grid and the robots on it.
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct World {
@@ -24,39 +61,432 @@ pub struct World {
     #[serde_as(as = "Vec<(_, _)>")]
     tiles: HashMap<Position, Tile>,
     robots: Vec<Robot>,
+    #[serde(default)]
+    move_policy: MovePolicy,
 }
 
 impl World {
+    /// Creates an empty world of `height` x `width` cells without robots.
     pub fn new(height: u32, width: u32) -> Self {
         Self {
             height,
             width,
             tiles: HashMap::new(),
             robots: Vec::new(),
+            move_policy: MovePolicy::default(),
         }
     }
 
+    /// Sets how moves into walls and the border are handled.
+    pub fn set_move_policy(&mut self, policy: MovePolicy) {
+        self.move_policy = policy;
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether `position` lies within the world.
+    pub fn in_bounds(&self, position: &Position) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && (position.x as u32) < self.width
+            && (position.y as u32) < self.height
+    }
+
+    /// The tile at `position`, `None` for unset cells.
+    pub fn tile(&self, position: &Position) -> Option<&Tile> {
+        self.tiles.get(position)
+    }
+
+    /// Whether `position` holds a wall or a closed door.
+    pub fn is_blocked(&self, position: &Position) -> bool {
+        matches!(
+            self.tiles.get(position),
+            Some(Tile::Wall | Tile::Door { open: false, .. })
+        )
+    }
+
+    /// Returns the solid robot standing on `position`, if any.
+    ///
+    /// Ghost robots never occupy a cell.
+    pub fn occupant(&self, position: &Position) -> Option<&Robot> {
+        self.robots
+            .iter()
+            .find(|robot| !robot.ghost && robot.position == *position)
+    }
+
+    /// The first cell a new robot can stand on, from south to north and
+    /// west to east.
+    pub fn free_cell(&self) -> Option<Position> {
+        (0..self.height as i32)
+            .flat_map(|y| (0..self.width as i32).map(move |x| Position::new(x, y)))
+            .find(|position| !self.is_blocked(position) && self.occupant(position).is_none())
+    }
+
+    /// Marks the robot `name` as ghost so it can overlap with other robots.
+    pub fn set_ghost(&mut self, name: &str, ghost: bool) -> Result<(), MovementError> {
+        let robot = self
+            .robots
+            .iter_mut()
+            .find(|robot| robot.name == name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        robot.ghost = ghost;
+        Ok(())
+    }
+
+    /// Puts `tile` at `position`, replacing what was there.
     pub fn add_tile(&mut self, position: Position, tile: Tile) {
         self.tiles.insert(position, tile);
     }
+    /// Adds a new robot `name` at `(0/0)`.
     pub fn add_robot_new(&mut self, name: String) {
         self.robots.push(Robot::new(name));
     }
+    /// Adds `robot` as it is.
     pub fn add_robot_existing(&mut self, robot: Robot) {
         self.robots.push(robot);
     }
+    /// Takes robot `name` out of the world.
+    pub fn remove_robot(&mut self, name: &str) -> Option<Robot> {
+        let index = self.robots.iter().position(|robot| robot.name == name)?;
+        Some(self.robots.remove(index))
+    }
+
+    /// All robots, in the order they were added.
+    pub fn robots(&self) -> &[Robot] {
+        &self.robots
+    }
+
+    /// The robot `name`, if there is one.
+    pub fn get_robot(&self, name: &str) -> Option<&Robot> {
+        self.robots.iter().find(|robot| robot.name == name)
+    }
+
+    /// The robot `name` for changing it, if there is one.
+    pub fn get_robot_mut(&mut self, name: &str) -> Option<&mut Robot> {
+        self.robots.iter_mut().find(|robot| robot.name == name)
+    }
+
+    /// Where the robot `name` stands.
     pub fn get_robot_position(&self, name: &str) -> Option<Position> {
-        self.robots
-            .iter()
-            .find(|robot| robot.name == name)
-            .and_then(|robot| Some(robot.position.clone()))
+        self.get_robot(name).map(|robot| robot.position.clone())
     }
 
+    /// Moves the robot `name`, checking every cell on the way.
+    ///
+    /// On a wall, a closed door, another robot, the world border or an empty battery the
+    /// robot either stops on the last legal cell or stays put, depending on
+    /// the [`MovePolicy`]. The error is returned in both cases. Every step
+    /// taken costs [`ENERGY_PER_STEP`] and is recorded in the robot's
+    /// [`Robot::visits`]. Ghost robots pass through
+    /// other robots and are passed through.
     pub fn move_robot(&mut self, name: &str, direction: Direction) -> Result<(), MovementError> {
-        self.robots
-            .iter_mut()
-            .find(|robot| robot.name == name)
-            .and_then(|robot| Some(robot.move_robot(direction).ok()?));
+        let index = self
+            .robots
+            .iter()
+            .position(|robot| robot.name == name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        let trajectory = self.robots[index].trajectory(&direction)?;
+        let ghost = self.robots[index].ghost;
+        let mut energy = self.robots[index].state_of_charge;
+
+        let mut passed = Vec::new();
+        let mut result = Ok(());
+        for position in trajectory {
+            if energy < ENERGY_PER_STEP {
+                result = Err(MovementError::BatteryEmpty);
+                break;
+            }
+            if !self.in_bounds(&position) {
+                result = Err(MovementError::OutOfBounds);
+                break;
+            }
+            if self.is_blocked(&position) {
+                result = Err(MovementError::Blocked { at: position });
+                break;
+            }
+            if let Some(other) = self.occupant(&position).filter(|_| !ghost) {
+                result = Err(MovementError::Collision {
+                    with: other.name.clone(),
+                });
+                break;
+            }
+            energy -= ENERGY_PER_STEP;
+            passed.push(position);
+        }
+
+        if result.is_err() && self.move_policy == MovePolicy::RejectWhole {
+            return result;
+        }
+        let robot = &mut self.robots[index];
+        if let Some(position) = passed.last() {
+            if robot.visits.is_empty() {
+                robot.visits.record(robot.position.clone());
+            }
+            robot.position = position.clone();
+            robot.state_of_charge = energy;
+            for position in passed {
+                robot.visits.record(position);
+            }
+        }
+        robot.turn(&direction);
+        result
+    }
+
+    /// Appends `command` to the queue of robot `name`, to be executed by [`World::step`].
+    pub fn queue_command(&mut self, name: &str, command: Command) -> Result<(), MovementError> {
+        self.get_robot_mut(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?
+            .commands
+            .push_back(command);
         Ok(())
     }
+
+    /// Appends all commands of `program` to the queue of robot `name`.
+    pub fn load_program(&mut self, name: &str, program: Program) -> Result<(), MovementError> {
+        self.get_robot_mut(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?
+            .commands
+            .extend(program.commands);
+        Ok(())
+    }
+
+    /// Drops all pending commands of robot `name` and returns how many there were.
+    pub fn clear_commands(&mut self, name: &str) -> Result<usize, MovementError> {
+        let robot = self
+            .get_robot_mut(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        let cleared = robot.commands.len();
+        robot.commands.clear();
+        Ok(cleared)
+    }
+
+    /// Lets robot `name` explore the world on its own from the next tick on.
+    ///
+    /// Restarts a running exploration. See [`crate::explore`].
+    pub fn start_exploration(&mut self, name: &str) -> Result<(), MovementError> {
+        self.get_robot_mut(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?
+            .exploration = Some(Exploration::default());
+        Ok(())
+    }
+
+    /// Replaces the commands of robot `name` with a route over every
+    /// reachable free cell, see [`coverage::plan`], and starts tracking its
+    /// visits anew.
+    ///
+    /// Returns how well the planned route covers the world.
+    pub fn cover(&mut self, name: &str) -> Result<CoverageReport, MovementError> {
+        let robot = self
+            .get_robot(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        let path = coverage::plan(self, &robot.position).map_err(|e| match e {
+            PlanError::OutOfBounds(_) => MovementError::OutOfBounds,
+            PlanError::Blocked(at) => MovementError::Blocked { at },
+            PlanError::Unreachable => MovementError::Blocked {
+                at: robot.position.clone(),
+            },
+        })?;
+        let report = CoverageReport::new(self, &path.cells);
+        let commands = path.directions(robot.heading).into_iter().map(Command::from);
+        let robot = self
+            .get_robot_mut(name)
+            .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+        robot.commands = commands.collect();
+        robot.visits = Visits::default();
+        Ok(report)
+    }
+
+    /// Replaces the commands of every robot in `schedule` with its plan,
+    /// one cell or wait per tick, see [`crate::planner::multi`].
+    ///
+    /// The plans only stay collision-free if they start with the next tick
+    /// and no other commands are queued in between.
+    pub fn load_schedule(&mut self, schedule: &Schedule) -> Result<(), MovementError> {
+        for (name, cells) in &schedule.plans {
+            let robot = self
+                .get_robot_mut(name)
+                .ok_or_else(|| MovementError::UnknownRobot(name.to_string()))?;
+            robot.commands = multi::commands(cells, robot.heading).into();
+        }
+        Ok(())
+    }
+
+    /// Advances the world by one simulation tick.
+    ///
+    /// In this order:
+    /// - doors whose period is due toggle, unless a robot stands in them,
+    /// - exploring robots without pending commands queue their next one,
+    /// - every robot works on the front command of its queue. A move running
+    ///   into another robot stays queued and is retried next tick, any other
+    ///   failing command is dropped. [`Command::Wait`] stays queued for its
+    ///   number of ticks, [`Command::ChargeUntilFull`] until the battery is full,
+    /// - robots standing on a [`Tile::ChargePad`] gain [`CHARGE_PER_TICK`],
+    /// - every robot scans its surroundings and updates its belief map.
+    ///
+    /// Returns what changed.
+    pub fn step(&mut self, tick: u64) -> Vec<WorldEvent> {
+        let mut events = self.toggle_doors(tick);
+        events.extend(self.explore());
+        events.extend(self.execute_commands());
+        events.extend(self.recharge());
+        self.observe();
+        events
+    }
+
+    /// Lets every idle exploring robot decide on its next command.
+    ///
+    /// Reports changed coverage and the end of explorations.
+    fn explore(&mut self) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        for index in 0..self.robots.len() {
+            if !self.robots[index].commands.is_empty() {
+                continue;
+            }
+            let Some(mut exploration) = self.robots[index].exploration.take() else {
+                continue;
+            };
+            let decision = exploration.decide(self, &self.robots[index]);
+            let robot = &mut self.robots[index];
+            let name = robot.name.clone();
+            let coverage = robot.belief.as_ref().map_or(0.0, OccupancyGrid::coverage);
+            match decision {
+                Decision::Finished => {
+                    events.push(WorldEvent::ExplorationFinished { name, coverage });
+                    continue;
+                }
+                Decision::Command(command) => robot.commands.push_back(command),
+                Decision::Wait => {}
+            }
+            if coverage != exploration.coverage {
+                exploration.coverage = coverage;
+                events.push(WorldEvent::ExplorationProgress { name, coverage });
+            }
+            robot.exploration = Some(exploration);
+        }
+        events
+    }
+
+    /// Integrates a lidar scan of every robot into its belief map.
+    fn observe(&mut self) {
+        for index in 0..self.robots.len() {
+            let robot = &self.robots[index];
+            let Ok(readings) = robot.lidar.scan(self, &robot.name) else {
+                continue;
+            };
+            let (width, height) = (self.width, self.height);
+            let robot = &mut self.robots[index];
+            robot
+                .belief
+                .get_or_insert_with(|| OccupancyGrid::new(width, height))
+                .integrate(&robot.position, robot.heading, &readings);
+        }
+    }
+
+    fn toggle_doors(&mut self, tick: u64) -> Vec<WorldEvent> {
+        let occupied: HashSet<Position> = self
+            .robots
+            .iter()
+            .map(|robot| robot.position.clone())
+            .collect();
+        let mut events = Vec::new();
+        for (position, tile) in &mut self.tiles {
+            if let Tile::Door { open, period } = tile
+                && *period > 0
+                && tick.is_multiple_of(u64::from(*period))
+                && !occupied.contains(position)
+            {
+                *open = !*open;
+                events.push(WorldEvent::TileChanged {
+                    position: position.clone(),
+                    tile: tile.clone(),
+                });
+            }
+        }
+        events
+    }
+
+    fn execute_commands(&mut self) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        for index in 0..self.robots.len() {
+            let Some(command) = self.robots[index].commands.front().cloned() else {
+                continue;
+            };
+            let name = self.robots[index].name.clone();
+            // Ok(true) when the command is done, Ok(false) to keep it for the next tick
+            let result = match command.direction() {
+                Some(direction) => {
+                    let moved = self.move_robot(&name, direction);
+                    let robot = &self.robots[index];
+                    events.push(WorldEvent::RobotMoved {
+                        name: name.clone(),
+                        position: robot.position.clone(),
+                        heading: robot.heading,
+                    });
+                    match moved {
+                        Ok(()) => Ok(true),
+                        Err(MovementError::Collision { .. }) => Ok(false),
+                        Err(error) => Err(error),
+                    }
+                }
+                None => self.control(index, &command),
+            };
+            let robot = &mut self.robots[index];
+            match result {
+                Ok(false) => {}
+                Ok(true) => {
+                    robot.commands.pop_front();
+                }
+                Err(error) => {
+                    robot.commands.pop_front();
+                    events.push(WorldEvent::CommandFailed { name, error });
+                }
+            }
+        }
+        events
+    }
+
+    /// Works on a command of robot `index` that does not move it.
+    ///
+    /// Returns whether the command is done.
+    fn control(&mut self, index: usize, command: &Command) -> Result<bool, MovementError> {
+        let robot = &mut self.robots[index];
+        match *command {
+            Command::Wait { ticks } if ticks > 1 => {
+                if let Some(front) = robot.commands.front_mut() {
+                    *front = Command::Wait { ticks: ticks - 1 };
+                }
+                Ok(false)
+            }
+            Command::ChargeUntilFull if robot.state_of_charge < FULL_CHARGE => {
+                match self.tiles.get(&robot.position) {
+                    Some(Tile::ChargePad) => Ok(false),
+                    _ => Err(MovementError::NoChargePad),
+                }
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn recharge(&mut self) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        for robot in &mut self.robots {
+            let on_pad = matches!(self.tiles.get(&robot.position), Some(Tile::ChargePad));
+            if on_pad && robot.state_of_charge < FULL_CHARGE {
+                robot.state_of_charge = robot.state_of_charge.saturating_add(CHARGE_PER_TICK);
+                events.push(WorldEvent::RobotCharged {
+                    name: robot.name.clone(),
+                    state_of_charge: robot.state_of_charge,
+                });
+            }
+        }
+        events
+    }
 }
@@ -0,0 +1,384 @@
+//! Text maps of worlds, see [`World::from_ascii`].
+//!
+//! A map is a block of rows followed by a blank line and a legend:
+//!
+//! ```text
+//! WWWWWWWW
+//! Wr...1CW
+//! W..k.W.W
+//! WWWWWWWW
+//!
+//! r = robot rusty east
+//! k = robot karl north on C
+//! 1 = door closed 4
+//! ```
+//!
+//! The first row is the northmost one, the first column the westmost one.
+//! Rows shorter than the longest one are filled up with empty cells; as the
+//! first blank line ends the map, a row of empty cells needs some `.`.
+//!
+//! | Character | Cell                               |
+//! |-----------|------------------------------------|
+//! | `.`, ` `  | empty                              |
+//! | `W`       | [`Tile::Wall`]                     |
+//! | `C`       | [`Tile::ChargePad`]                |
+//! | `/`       | open [`Tile::Door`], never closes  |
+//! | `+`       | closed [`Tile::Door`], never opens |
+//!
+//! Every other character must be explained by the legend, either as a robot
+//! (`robot <name> [heading] [on <tile character>]`, heading north by default)
+//! or as a door with a period (`door <open|closed> <period>`). Robots are
+//! only placed, their battery, commands and the like start out fresh.
+
+use crate::heading::Heading;
+use crate::position::Position;
+use crate::robot::Robot;
+use crate::world::{Tile, World};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// What a character of a map stands for.
+#[derive(Debug, Clone)]
+enum Symbol {
+    Empty,
+    Tile(Tile),
+    Robot {
+        name: String,
+        heading: Heading,
+        on: Option<Tile>,
+    },
+}
+
+/// Meaning of the characters every map knows.
+fn builtin(c: char) -> Option<Symbol> {
+    let symbol = match c {
+        '.' | ' ' => Symbol::Empty,
+        'W' => Symbol::Tile(Tile::Wall),
+        'C' => Symbol::Tile(Tile::ChargePad),
+        '/' => Symbol::Tile(Tile::Door {
+            open: true,
+            period: 0,
+        }),
+        '+' => Symbol::Tile(Tile::Door {
+            open: false,
+            period: 0,
+        }),
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+/// Character of a tile in maps, `None` for doors that need a legend entry.
+fn glyph(tile: &Tile) -> Option<char> {
+    match tile {
+        Tile::Empty => Some('.'),
+        Tile::Wall => Some('W'),
+        Tile::ChargePad => Some('C'),
+        Tile::Door {
+            open: true,
+            period: 0,
+        } => Some('/'),
+        Tile::Door {
+            open: false,
+            period: 0,
+        } => Some('+'),
+        Tile::Door { .. } => None,
+    }
+}
+
+fn heading_name(heading: Heading) -> &'static str {
+    match heading {
+        Heading::North => "north",
+        Heading::East => "east",
+        Heading::South => "south",
+        Heading::West => "west",
+    }
+}
+
+fn parse_heading(word: &str) -> Option<Heading> {
+    match word.to_lowercase().as_str() {
+        "north" | "n" => Some(Heading::North),
+        "east" | "e" => Some(Heading::East),
+        "south" | "s" => Some(Heading::South),
+        "west" | "w" => Some(Heading::West),
+        _ => None,
+    }
+}
+
+/// What went wrong reading a map.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsciiErrorKind {
+    /// A map character neither built in nor in the legend.
+    UnknownCharacter(char),
+    /// A legend line that does not follow the syntax.
+    InvalidLegend(String),
+    /// A legend entry for a character that already has a meaning.
+    DuplicateSymbol(char),
+    /// Two robots with the same name.
+    DuplicateRobot(String),
+    /// A robot character used more than once on the map.
+    RobotPlacedTwice(char),
+    /// A robot of the legend that is not on the map.
+    MissingRobot(String),
+}
+
+/// A map that could not be read, with the 1-based line and column at fault.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsciiError {
+    /// Line of the map, the legend lines counting on after the rows.
+    pub line: usize,
+    /// Column within the line.
+    pub column: usize,
+    /// What is wrong there.
+    pub kind: AsciiErrorKind,
+}
+
+impl fmt::Display for AsciiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiErrorKind::UnknownCharacter(c) => {
+                write!(f, "character `{c}` is neither a tile nor in the legend")
+            }
+            AsciiErrorKind::InvalidLegend(line) => write!(f, "invalid legend entry `{line}`"),
+            AsciiErrorKind::DuplicateSymbol(c) => write!(f, "character `{c}` is already taken"),
+            AsciiErrorKind::DuplicateRobot(name) => write!(f, "robot `{name}` is defined twice"),
+            AsciiErrorKind::RobotPlacedTwice(c) => write!(f, "robot `{c}` is placed twice"),
+            AsciiErrorKind::MissingRobot(name) => write!(f, "robot `{name}` is not on the map"),
+        }
+    }
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.kind, self.line, self.column)
+    }
+}
+
+impl std::error::Error for AsciiError {}
+
+/// Parses the tile part of a legend entry, `words` follow the `door` keyword.
+fn parse_door(words: &[&str]) -> Option<Tile> {
+    let [state, period] = words else {
+        return None;
+    };
+    let open = match *state {
+        "open" => true,
+        "closed" => false,
+        _ => return None,
+    };
+    Some(Tile::Door {
+        open,
+        period: period.parse().ok()?,
+    })
+}
+
+/// Parses one legend line like `r = robot rusty east on C`.
+fn parse_legend(line: &str, legend: &HashMap<char, Symbol>) -> Option<(char, Symbol)> {
+    let (key, value) = line.split_once('=')?;
+    let mut key = key.trim().chars();
+    let (Some(c), None) = (key.next(), key.next()) else {
+        return None;
+    };
+    let words: Vec<&str> = value.split_whitespace().collect();
+    let symbol = match words.as_slice() {
+        ["door", rest @ ..] => Symbol::Tile(parse_door(rest)?),
+        ["robot", name, rest @ ..] => {
+            let (heading, rest) = match rest.split_first() {
+                Some((word, rest)) if *word != "on" => (parse_heading(word)?, rest),
+                _ => (Heading::default(), rest),
+            };
+            let on = match rest {
+                [] => None,
+                ["on", tile] => {
+                    let mut tile = tile.chars();
+                    let (Some(tile), None) = (tile.next(), tile.next()) else {
+                        return None;
+                    };
+                    match builtin(tile).or_else(|| legend.get(&tile).cloned())? {
+                        Symbol::Empty => None,
+                        Symbol::Tile(tile) => Some(tile),
+                        Symbol::Robot { .. } => return None,
+                    }
+                }
+                _ => return None,
+            };
+            Symbol::Robot {
+                name: name.to_string(),
+                heading,
+                on,
+            }
+        }
+        _ => return None,
+    };
+    Some((c, symbol))
+}
+
+impl World {
+    /// Reads a world from a text map, see [`crate::world::ascii`] for the format.
+    ///
+    /// # Errors
+    /// Returns an [`AsciiError`] pointing at the first offending character or
+    /// legend line.
+    pub fn from_ascii(map: &str) -> Result<World, AsciiError> {
+        let lines: Vec<&str> = map.lines().collect();
+        let rows = lines
+            .iter()
+            .position(|line| line.trim().is_empty())
+            .unwrap_or(lines.len());
+
+        // doors go first, robots may stand on them
+        let mut legend: HashMap<char, Symbol> = HashMap::new();
+        let mut names: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut entries: Vec<(usize, &str)> = lines
+            .iter()
+            .enumerate()
+            .skip(rows)
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (index, *line))
+            .collect();
+        entries.sort_by_key(|(_, line)| line.contains("robot"));
+        for (index, line) in entries {
+            let error = |kind| AsciiError {
+                line: index + 1,
+                column: line.len() - line.trim_start().len() + 1,
+                kind,
+            };
+            let (c, symbol) = parse_legend(line, &legend)
+                .ok_or_else(|| error(AsciiErrorKind::InvalidLegend(line.trim().to_string())))?;
+            if builtin(c).is_some() || legend.contains_key(&c) {
+                return Err(error(AsciiErrorKind::DuplicateSymbol(c)));
+            }
+            if let Symbol::Robot { name, .. } = &symbol {
+                let at = error(AsciiErrorKind::DuplicateRobot(name.clone()));
+                if names.insert(name.clone(), (at.line, at.column)).is_some() {
+                    return Err(at);
+                }
+            }
+            legend.insert(c, symbol);
+        }
+
+        let width = lines[..rows]
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        let mut world = World::new(rows as u32, width as u32);
+        let mut placed = HashSet::new();
+        for (index, line) in lines[..rows].iter().enumerate() {
+            let y = (rows - 1 - index) as i32;
+            for (x, c) in line.chars().enumerate() {
+                let error = |kind| AsciiError {
+                    line: index + 1,
+                    column: x + 1,
+                    kind,
+                };
+                let position = Position::new(x as i32, y);
+                let symbol = builtin(c)
+                    .or_else(|| legend.get(&c).cloned())
+                    .ok_or_else(|| error(AsciiErrorKind::UnknownCharacter(c)))?;
+                match symbol {
+                    Symbol::Empty => {}
+                    Symbol::Tile(tile) => world.add_tile(position, tile),
+                    Symbol::Robot { name, heading, on } => {
+                        if !placed.insert(c) {
+                            return Err(error(AsciiErrorKind::RobotPlacedTwice(c)));
+                        }
+                        if let Some(tile) = on {
+                            world.add_tile(position.clone(), tile);
+                        }
+                        let mut robot = Robot::new(name);
+                        robot.position = position;
+                        robot.heading = heading;
+                        world.add_robot_existing(robot);
+                    }
+                }
+            }
+        }
+        let missing = names
+            .into_iter()
+            .filter(|(name, _)| world.get_robot(name).is_none())
+            .min_by_key(|(_, at)| *at);
+        if let Some((name, (line, column))) = missing {
+            return Err(AsciiError {
+                line,
+                column,
+                kind: AsciiErrorKind::MissingRobot(name),
+            });
+        }
+        Ok(world)
+    }
+
+    /// Writes the world as a text map that [`World::from_ascii`] reads back.
+    ///
+    /// Only tiles, robot names, positions and headings are kept. Robots
+    /// outside the world and all but the first of robots sharing a cell
+    /// are left out; names must not contain whitespace to be read back.
+    pub fn to_ascii(&self) -> String {
+        // letters for robots and doors with a period, tiles use other characters
+        let mut symbols = ('a'..='z')
+            .chain(('A'..='Z').filter(|c| builtin(*c).is_none()))
+            .chain(
+                ('0'..='9')
+                    .chain('\u{c0}'..)
+                    .filter(|c| c.is_alphanumeric()),
+            );
+        let mut doors: Vec<(Tile, char)> = Vec::new();
+        let mut legend = Vec::new();
+        let mut char_of = |tile: &Tile, legend: &mut Vec<String>| match glyph(tile) {
+            Some(c) => c,
+            None => {
+                if let Some((_, c)) = doors.iter().find(|(door, _)| door == tile) {
+                    return *c;
+                }
+                let c = symbols.next().unwrap_or('?');
+                if let Tile::Door { open, period } = tile {
+                    let state = if *open { "open" } else { "closed" };
+                    legend.push(format!("{c} = door {state} {period}"));
+                }
+                doors.push((tile.clone(), c));
+                c
+            }
+        };
+
+        // in reading order, so that doors get the same characters every time
+        let mut cells: HashMap<Position, char> = HashMap::new();
+        for y in (0..self.height as i32).rev() {
+            for x in 0..self.width as i32 {
+                let position = Position::new(x, y);
+                if let Some(tile) = self.tile(&position) {
+                    let c = char_of(tile, &mut legend);
+                    cells.insert(position, c);
+                }
+            }
+        }
+        let mut placed = HashSet::new();
+        for robot in &self.robots {
+            if !self.in_bounds(&robot.position) || !placed.insert(robot.position.clone()) {
+                continue;
+            }
+            let c = symbols.next().unwrap_or('?');
+            let mut entry = format!("{c} = robot {} {}", robot.name, heading_name(robot.heading));
+            if let Some(tile) = cells.get(&robot.position).filter(|tile| **tile != '.') {
+                entry.push_str(&format!(" on {tile}"));
+            }
+            legend.push(entry);
+            cells.insert(robot.position.clone(), c);
+        }
+
+        let mut map = String::new();
+        for y in (0..self.height as i32).rev() {
+            for x in 0..self.width as i32 {
+                map.push(*cells.get(&Position::new(x, y)).unwrap_or(&'.'));
+            }
+            map.push('\n');
+        }
+        if !legend.is_empty() {
+            map.push('\n');
+            for entry in legend {
+                map.push_str(&entry);
+                map.push('\n');
+            }
+        }
+        map
+    }
+}
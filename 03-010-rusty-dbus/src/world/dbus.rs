@@ -1,50 +1,702 @@
+mod error;
+pub mod robot;
+
+pub use error::RustyError;
+
+use crate::command::Program;
+use crate::mapping::Belief;
+use crate::moveable::Direction;
+use crate::planner::Planner;
+use crate::planner::multi::{MultiPlanError, MultiPlanner, Task};
 use crate::position::Position;
+use crate::robot::Robot;
+use crate::sensor::{Hit, Lidar, MAX_BEAMS, MAX_RANGE};
+use crate::simulation::{TickEvent, WorldEvent};
+use crate::world::validate::Diagnostic;
 use crate::world::{Tile, World};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use zbus::interface;
-use zbus::object_server::SignalEmitter;
+use tokio::sync::{Mutex, Notify, broadcast};
+use tokio::task::{AbortHandle, Id};
+use zbus::object_server::{ObjectServer, SignalEmitter};
+use zbus::{Connection, interface};
 
 pub struct WorldDbus {
     world: Arc<Mutex<World>>,
+    /// Tick events of the simulation, navigations step along with them.
+    ticks: broadcast::Sender<TickEvent>,
+    /// Running navigations by robot name.
+    navigations: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    /// Notified when a client asks the service to quit.
+    shutdown: Arc<Notify>,
 }
 
 impl WorldDbus {
-    pub fn new(world: Arc<Mutex<World>>) -> Self {
-        Self { world }
+    pub fn new(
+        world: Arc<Mutex<World>>,
+        ticks: broadcast::Sender<TickEvent>,
+        shutdown: Arc<Notify>,
+    ) -> Self {
+        Self {
+            world,
+            ticks,
+            navigations: Arc::new(Mutex::new(HashMap::new())),
+            shutdown,
+        }
+    }
+}
+
+/// Steps `robot_name` along `directions`, one per tick, and reports every step.
+///
+/// Removes its own entry from `navigations` once done, unless it has been
+/// replaced by a newer navigation in the meantime.
+async fn navigate(
+    world: Arc<Mutex<World>>,
+    navigations: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    connection: Connection,
+    robot_name: String,
+    directions: Vec<Direction>,
+    mut ticks: broadcast::Receiver<TickEvent>,
+) {
+    let mut outcome = Ok(());
+    for direction in directions {
+        if let Err(broadcast::error::RecvError::Closed) = ticks.recv().await {
+            outcome = Err("simulation stopped".to_string());
+            break;
+        }
+        let moved = world.lock().await.move_robot(&robot_name, direction);
+        let _ = announce_robot(&connection, &world, &robot_name).await;
+        if let Err(e) = moved {
+            outcome = Err(format!("{e:?}"));
+            break;
+        }
+    }
+
+    let own_id: Id = tokio::task::id();
+    let mut navigations = navigations.lock().await;
+    if navigations
+        .get(&robot_name)
+        .is_some_and(|handle| handle.id() == own_id)
+    {
+        navigations.remove(&robot_name);
+    }
+    drop(navigations);
+
+    let Ok(emitter) = SignalEmitter::new(&connection, "/") else {
+        return;
+    };
+    let _ = match outcome {
+        Ok(()) => {
+            info!("{robot_name} reached its goal");
+            WorldDbus::navigation_completed(&emitter, robot_name).await
+        }
+        Err(reason) => {
+            warn!("Navigation of {robot_name} failed: {reason}");
+            WorldDbus::navigation_failed(&emitter, robot_name, reason).await
+        }
+    };
+}
+
+/// Tells clients about the current state of robot `name`.
+///
+/// Emits the `Robot` signal on the root object and `PropertiesChanged`
+/// on the robot's own object. A robot removed in the meantime is skipped.
+pub(crate) async fn announce_robot(
+    connection: &Connection,
+    world: &Mutex<World>,
+    name: &str,
+) -> zbus::Result<()> {
+    let pose = world
+        .lock()
+        .await
+        .get_robot(name)
+        .map(|robot| (robot.position.clone(), robot.heading));
+    let Some((position, heading)) = pose else {
+        return Ok(());
+    };
+    let emitter = SignalEmitter::new(connection, "/")?;
+    WorldDbus::robot(
+        &emitter,
+        name.to_string(),
+        position.x,
+        position.y,
+        heading.to_string(),
+    )
+    .await?;
+    robot::notify_changed(connection.object_server(), name).await
+}
+
+/// Moves robot `name` in the named `direction`, see [`parse_direction`].
+///
+/// Stops at the first failing single move. [`World::move_robot`] refuses
+/// steps out of the world, into walls and onto other robots, so moves need
+/// no further validation.
+pub(crate) fn apply_move(
+    world: &mut World,
+    name: &str,
+    direction: &str,
+    steps: i32,
+) -> Result<(), RustyError> {
+    if world.get_robot(name).is_none() {
+        return Err(RustyError::UnknownRobot(format!("Robot {name} not found")));
+    }
+    for direction in parse_direction(direction, steps)? {
+        world.move_robot(name, direction)?;
+    }
+    Ok(())
+}
+
+/// Adds robot `name` on the first free cell, see [`World::free_cell`].
+///
+/// Fails with `InvalidWorld` if no cell is free or the robot does not pass
+/// [`World::validate_robot`], e.g. because the name is taken.
+pub(crate) fn place_robot(world: &mut World, name: &str) -> Result<(), RustyError> {
+    let mut robot = Robot::new(name.to_string());
+    robot.position = world
+        .free_cell()
+        .ok_or_else(|| RustyError::InvalidWorld(format!("No free cell for robot {name}")))?;
+    validated(&world.validate_robot(&robot))?;
+    world.add_robot_existing(robot);
+    Ok(())
+}
+
+/// Puts `tile` on `position`.
+///
+/// Fails with `InvalidWorld` and leaves the cell as it was if it does not
+/// pass [`World::validate_cell`] afterwards, e.g. for a wall on a robot.
+pub(crate) fn place_tile(
+    world: &mut World,
+    position: Position,
+    tile: Tile,
+) -> Result<(), RustyError> {
+    let previous = world.tiles.insert(position.clone(), tile);
+    let result = validated(&world.validate_cell(&position));
+    if result.is_err() {
+        match previous {
+            Some(previous) => world.tiles.insert(position, previous),
+            None => world.tiles.remove(&position),
+        };
+    }
+    result
+}
+
+/// Turns the [`Diagnostic`]s of a change made over D-Bus into its result.
+///
+/// Logs every problem found and returns them as [`RustyError::InvalidWorld`].
+pub(crate) fn validated(diagnostics: &[Diagnostic]) -> Result<(), RustyError> {
+    let problems: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    for problem in &problems {
+        warn!("Invalid world: {problem}");
+    }
+    Err(RustyError::InvalidWorld(problems.join(", ")))
+}
+
+/// Forwards the tick events of the simulation to D-Bus.
+///
+/// Emits `Tick` for every tick, announces changed robots, emits `Tile`
+/// for changed tiles, `CommandFailed` for dropped commands,
+/// `ScriptFailed` for broken controller scripts and `ExplorationProgress`
+/// and `ExplorationFinished` for exploring robots.
+///
+/// A signal that cannot be emitted, e.g. for a robot removed in the
+/// meantime, is logged and skipped; only a stopped simulation ends the loop.
+pub async fn forward_events(
+    connection: Connection,
+    world: Arc<Mutex<World>>,
+    mut ticks: broadcast::Receiver<TickEvent>,
+) -> zbus::Result<()> {
+    let emitter = SignalEmitter::new(&connection, "/")?;
+    loop {
+        let event = match ticks.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("D-Bus missed {missed} ticks");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        if let Err(e) = WorldDbus::tick(&emitter, event.tick).await {
+            warn!("Could not forward tick {}: {e}", event.tick);
+        }
+        for change in event.events {
+            if let Err(e) = forward_event(&connection, &emitter, &world, change).await {
+                warn!("Could not forward a world event of tick {}: {e}", event.tick);
+            }
+        }
+    }
+}
+
+/// Emits the signals of a single world event, see [`forward_events`].
+async fn forward_event(
+    connection: &Connection,
+    emitter: &SignalEmitter<'_>,
+    world: &Mutex<World>,
+    change: WorldEvent,
+) -> zbus::Result<()> {
+    match change {
+        WorldEvent::RobotMoved { name, .. } | WorldEvent::RobotCharged { name, .. } => {
+            announce_robot(connection, world, &name).await
+        }
+        WorldEvent::CommandFailed { name, error } => {
+            WorldDbus::command_failed(emitter, name, format!("{error:?}")).await
+        }
+        WorldEvent::ScriptFailed { name, error } => {
+            WorldDbus::script_failed(emitter, name, error).await
+        }
+        WorldEvent::ExplorationProgress { name, coverage } => {
+            WorldDbus::exploration_progress(emitter, name, coverage).await
+        }
+        WorldEvent::ExplorationFinished { name, coverage } => {
+            info!("{name} finished exploring with {coverage:.1}% coverage");
+            WorldDbus::exploration_finished(emitter, name, coverage).await
+        }
+        WorldEvent::TileChanged { position, tile } => {
+            let tile =
+                serde_json::to_string(&tile).map_err(|e| zbus::Error::Failure(e.to_string()))?;
+            WorldDbus::tile(emitter, tile, position.x, position.y).await
+        }
+    }
+}
+
+/// Exports an object for every robot of `world`.
+pub async fn export_robots(server: &ObjectServer, world: Arc<Mutex<World>>) -> zbus::Result<()> {
+    let names: Vec<String> = world
+        .lock()
+        .await
+        .robots
+        .iter()
+        .map(|robot| robot.name.clone())
+        .collect();
+    for name in names {
+        if !robot::export(server, world.clone(), &name).await? {
+            warn!("Robot {name} shares its object path with another robot");
+        }
+    }
+    Ok(())
+}
+
+/// Translates a direction name and count into single moves.
+///
+/// `forward` becomes one move of `steps` cells, all other directions
+/// are repeated `steps` times.
+fn parse_direction(direction: &str, steps: i32) -> Result<Vec<Direction>, RustyError> {
+    let repeat = |direction: Direction| -> Result<Vec<Direction>, RustyError> {
+        let count = usize::try_from(steps)
+            .map_err(|_| RustyError::InvalidStep(format!("Invalid step count {steps}")))?;
+        Ok(vec![direction; count])
+    };
+    match direction.to_lowercase().as_str() {
+        "forward" => Ok(vec![Direction::Forward { step: steps }]),
+        "backwards" | "backward" => repeat(Direction::Backwards),
+        "left" => repeat(Direction::Left),
+        "right" => repeat(Direction::Right),
+        "turn_left" | "turnleft" => repeat(Direction::TurnLeft),
+        "turn_right" | "turnright" => repeat(Direction::TurnRight),
+        _ => Err(RustyError::InvalidDirection(format!(
+            "Unknown direction {direction}"
+        ))),
     }
 }
 
 #[interface(name = "org.example.something")]
 impl WorldDbus {
-    async fn add_robot(&self, robot_name: &str) {
-        self.world
-            .lock()
-            .await
-            .add_robot_new(robot_name.to_string());
+    /// Adds robot `robot_name` on the first free cell, see `World::free_cell`.
+    ///
+    /// Fails with `InvalidWorld` if the name is taken or no cell is free.
+    async fn add_robot(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        robot_name: &str,
+    ) -> Result<(), RustyError> {
+        place_robot(&mut *self.world.lock().await, robot_name)?;
+        if !robot::export(connection.object_server(), self.world.clone(), robot_name).await? {
+            warn!("Robot {robot_name} shares its object path with another robot");
+        }
+        announce_robot(connection, &self.world, robot_name).await?;
+        Ok(())
     }
 
-    async fn add_tile(&self, tile_name: &str, x: i32, y: i32) -> zbus::fdo::Result<()> {
+    /// Puts the tile `tile_name` (JSON like `"Wall"`) on `(x/y)`.
+    ///
+    /// Fails with `InvalidWorld` for cells outside the world and walls on robots.
+    async fn add_tile(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        tile_name: &str,
+        x: i32,
+        y: i32,
+    ) -> Result<(), RustyError> {
         let position = Position::new(x, y);
         let tile: Tile = serde_json::from_str(tile_name).map_err(|e| {
-            zbus::fdo::Error::UnknownObject(format!("Tile name {tile_name} is invalid ({e})"))
+            RustyError::InvalidTile(format!("Tile name {tile_name} is invalid ({e})"))
         })?;
-        self.world.lock().await.add_tile(position, tile);
+        place_tile(&mut *self.world.lock().await, position, tile)?;
+        Self::tile(&emitter, tile_name.to_string(), x, y).await?;
         Ok(())
     }
 
-    async fn get_robot(&self, robot_name: &str) -> zbus::fdo::Result<(i32, i32)> {
+    async fn get_robot(&self, robot_name: &str) -> Result<(i32, i32), RustyError> {
         let pos = self
             .world
             .lock()
             .await
             .get_robot_position(robot_name)
-            .ok_or(zbus::fdo::Error::Failed(format!(
-                "Robot {robot_name} not found"
-            )))?;
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
         Ok((pos.x, pos.y))
     }
 
+    /// Moves `robot_name` in `direction` (`forward`, `backwards`, `left`, `right`,
+    /// `turn_left` or `turn_right`).
+    ///
+    /// `forward` moves `steps` cells at once, the other directions are repeated
+    /// `steps` times. Emits `Robot` and `PropertiesChanged` on the robot's
+    /// object, even when the move failed halfway.
+    async fn move_robot(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        robot_name: &str,
+        direction: &str,
+        steps: i32,
+    ) -> Result<(), RustyError> {
+        let moved = apply_move(&mut *self.world.lock().await, robot_name, direction, steps);
+        if moved
+            .as_ref()
+            .is_err_and(|e| matches!(e, RustyError::UnknownRobot(_)))
+        {
+            return moved;
+        }
+        announce_robot(connection, &self.world, robot_name).await?;
+        moved
+    }
+
+    /// Queues a move for `robot_name`, executed by the simulation one per tick.
+    ///
+    /// Takes the same arguments as `MoveRobot`. Progress is reported with
+    /// `Robot` and `PropertiesChanged`, a failing move with `CommandFailed`.
+    async fn queue_move(
+        &self,
+        robot_name: &str,
+        direction: &str,
+        steps: i32,
+    ) -> Result<(), RustyError> {
+        let directions = parse_direction(direction, steps)?;
+        let mut world = self.world.lock().await;
+        for direction in directions {
+            world.queue_command(robot_name, direction.into())?;
+        }
+        Ok(())
+    }
+
+    /// Queues the program in the JSON or TOML file at `path` for `robot_name`.
+    ///
+    /// The path is resolved by the service. Returns the number of queued commands.
+    async fn load_program(&self, robot_name: &str, path: &str) -> Result<u32, RustyError> {
+        let program = Program::load(Path::new(path))
+            .map_err(|e| RustyError::InvalidProgram(format!("Could not load {path}: {e:?}")))?;
+        let count = program.commands.len() as u32;
+        self.world.lock().await.load_program(robot_name, program)?;
+        Ok(count)
+    }
+
+    /// Returns the pending commands of `robot_name`, next one first.
+    ///
+    /// Commands are written like `forward 2`, `turn_left` or `wait 3`.
+    async fn get_commands(&self, robot_name: &str) -> Result<Vec<String>, RustyError> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        Ok(robot.commands.iter().map(ToString::to_string).collect())
+    }
+
+    /// Drops all pending commands of `robot_name` and returns how many there were.
+    async fn clear_commands(&self, robot_name: &str) -> Result<u32, RustyError> {
+        let cleared = self.world.lock().await.clear_commands(robot_name)?;
+        Ok(cleared as u32)
+    }
+
+    /// Lets the Rhai script at `path` control `robot_name` from the next tick on.
+    ///
+    /// The path is resolved by the service. The script runs whenever the
+    /// robot has no commands left; compile and runtime errors are reported
+    /// with `ScriptFailed`. Replaces a previous controller.
+    async fn attach_controller(&self, robot_name: &str, path: &str) -> Result<(), RustyError> {
+        let mut world = self.world.lock().await;
+        let robot = world
+            .get_robot_mut(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        robot.controller = Some(path.into());
+        Ok(())
+    }
+
+    /// Scans the surroundings of `robot_name` with its lidar.
+    ///
+    /// Returns one `(angle, distance, hit, robot)` per beam: the angle in degrees
+    /// counter-clockwise from the robot's heading, the distance in cells, what
+    /// was hit (`wall`, `robot` or `nothing`) and the name of a hit robot.
+    async fn scan(
+        &self,
+        robot_name: &str,
+    ) -> Result<Vec<(f64, f64, String, String)>, RustyError> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        let readings = robot.lidar.scan(&world, robot_name)?;
+        Ok(readings
+            .into_iter()
+            .map(|reading| {
+                let kind = reading.hit.kind().to_string();
+                let other = match reading.hit {
+                    Hit::Robot(name) => name,
+                    _ => String::new(),
+                };
+                (reading.angle, reading.distance, kind, other)
+            })
+            .collect())
+    }
+
+    /// Returns what `robot_name` believes about the cells it has observed.
+    ///
+    /// One `(x, y, belief)` per known cell, `belief` is `free` or `occupied`.
+    /// Cells missing are unknown to the robot.
+    async fn get_belief_map(
+        &self,
+        robot_name: &str,
+    ) -> Result<Vec<(i32, i32, String)>, RustyError> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        let Some(belief) = &robot.belief else {
+            return Ok(Vec::new());
+        };
+        Ok(belief
+            .known()
+            .map(|(position, belief)| {
+                let belief = match belief {
+                    Belief::Occupied => "occupied",
+                    _ => "free",
+                };
+                (position.x, position.y, belief.to_string())
+            })
+            .collect())
+    }
+
+    /// Lets `robot_name` explore the world on its own, driving to the nearest
+    /// frontier of its belief map until nothing reachable is unknown.
+    ///
+    /// Low batteries are recharged on a charge pad the robot has seen. Progress
+    /// is reported with `ExplorationProgress`, the end with `ExplorationFinished`,
+    /// both with the known share of the map in percent. Restarts a running
+    /// exploration.
+    async fn explore(&self, robot_name: &str) -> Result<(), RustyError> {
+        Ok(self.world.lock().await.start_exploration(robot_name)?)
+    }
+
+    /// Lets `robot_name` visit every free cell it can reach, like a vacuum robot.
+    ///
+    /// Replaces the pending commands with a planned route, one cell per tick,
+    /// and starts tracking the robot's visits anew, see `GetCoverage`. Returns
+    /// the coverage ratio (`0.0` to `1.0`) and the revisit overhead of the route.
+    async fn cover(&self, robot_name: &str) -> Result<(f64, f64), RustyError> {
+        let report = self.world.lock().await.cover(robot_name)?;
+        Ok((report.ratio(), report.overhead()))
+    }
+
+    /// Returns the coverage ratio (`0.0` to `1.0`) and the revisit overhead
+    /// of the cells `robot_name` actually entered.
+    ///
+    /// The overhead is the number of revisits per distinct cell.
+    async fn get_coverage(&self, robot_name: &str) -> Result<(f64, f64), RustyError> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        let report = robot.visits.report(&world);
+        Ok((report.ratio(), report.overhead()))
+    }
+
+    /// Sets the number of `beams` and the `range` in cells of the lidar of `robot_name`.
+    async fn configure_lidar(
+        &self,
+        robot_name: &str,
+        beams: u32,
+        range: f64,
+    ) -> Result<(), RustyError> {
+        let lidar = Lidar { beams, range };
+        if !lidar.is_valid() {
+            return Err(RustyError::InvalidSensor(format!(
+                "Lidar needs 1 to {MAX_BEAMS} beams and a range up to {MAX_RANGE}"
+            )));
+        }
+        let mut world = self.world.lock().await;
+        let robot = world
+            .get_robot_mut(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        robot.lidar = lidar;
+        Ok(())
+    }
+
+    /// Stops the controller script of `robot_name`, queued commands stay.
+    async fn detach_controller(&self, robot_name: &str) -> Result<(), RustyError> {
+        let mut world = self.world.lock().await;
+        let robot = world
+            .get_robot_mut(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        robot.controller = None;
+        Ok(())
+    }
+
+    /// Removes `robot_name` from the world and emits `RobotRemoved`
+    /// as well as `InterfacesRemoved` for its object.
+    ///
+    /// A running navigation of the robot is stopped.
+    async fn remove_robot(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        #[zbus(object_server)] server: &ObjectServer,
+        robot_name: &str,
+    ) -> Result<(), RustyError> {
+        self.world
+            .lock()
+            .await
+            .remove_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        if let Some(navigation) = self.navigations.lock().await.remove(robot_name) {
+            navigation.abort();
+        }
+        robot::unexport(server, robot_name).await?;
+        Self::robot_removed(&emitter, robot_name.to_string()).await?;
+        Ok(())
+    }
+
+    /// Returns all robots as `(name, x, y)`.
+    async fn list_robots(&self) -> Vec<(String, i32, i32)> {
+        self.world
+            .lock()
+            .await
+            .robots
+            .iter()
+            .map(|robot| (robot.name.clone(), robot.position.x, robot.position.y))
+            .collect()
+    }
+
+    /// Returns all tiles as `(tile, x, y)`, the tile encoded like for `AddTile`.
+    async fn get_tiles(&self) -> zbus::fdo::Result<Vec<(String, i32, i32)>> {
+        self.world
+            .lock()
+            .await
+            .tiles
+            .iter()
+            .map(|(position, tile)| {
+                serde_json::to_string(tile)
+                    .map(|name| (name, position.x, position.y))
+                    .map_err(|e| zbus::fdo::Error::Failed(format!("Tile not encodable ({e})")))
+            })
+            .collect()
+    }
+
+    /// Plans a route for `robot_name` to `(x, y)` and drives it there, one step per tick.
+    ///
+    /// Returns as soon as the route is planned. Every step emits `Robot` and
+    /// `PropertiesChanged` on the robot's object, the end
+    /// is reported with `NavigationCompleted` or `NavigationFailed`. A running
+    /// navigation of the same robot is replaced.
+    ///
+    /// Fails with `UnknownRobot`, or with `OutOfBounds`, `Blocked` or `NoRoute`
+    /// if there is no route to the goal.
+    async fn navigate_to(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        robot_name: &str,
+        x: i32,
+        y: i32,
+    ) -> Result<(), RustyError> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(robot_name)
+            .ok_or_else(|| RustyError::UnknownRobot(format!("Robot {robot_name} not found")))?;
+        let path = Planner::default().plan(&world, &robot.position, &Position::new(x, y))?;
+        let directions = path.directions(robot.heading);
+        drop(world);
+
+        let mut navigations = self.navigations.lock().await;
+        if let Some(previous) = navigations.remove(robot_name) {
+            previous.abort();
+        }
+        let task = tokio::spawn(navigate(
+            self.world.clone(),
+            self.navigations.clone(),
+            connection.clone(),
+            robot_name.to_string(),
+            directions,
+            self.ticks.subscribe(),
+        ));
+        navigations.insert(robot_name.to_string(), task.abort_handle());
+        Ok(())
+    }
+
+    /// Sends several robots to their goals at once, given as `(name, x, y)`.
+    ///
+    /// Plans collision-free routes for all of them and replaces their pending
+    /// commands, so that the simulation moves them one cell per tick from the
+    /// next tick on. Returns the number of ticks until the last robot arrived.
+    async fn navigate_all(&self, goals: Vec<(String, i32, i32)>) -> Result<u32, RustyError> {
+        let tasks: Vec<Task> = goals
+            .into_iter()
+            .map(|(name, x, y)| Task {
+                name,
+                goal: Position::new(x, y),
+            })
+            .collect();
+        let mut world = self.world.lock().await;
+        let schedule = MultiPlanner::default()
+            .plan(&world, &tasks)
+            .map_err(|e| match e {
+                MultiPlanError::UnknownRobot(name) => {
+                    RustyError::UnknownRobot(format!("Robot {name} not found"))
+                }
+                e => RustyError::NoRoute(format!("No collision-free routes: {e:?}")),
+            })?;
+        world.load_schedule(&schedule)?;
+        Ok(schedule.makespan() as u32)
+    }
+
+    /// Stops a running navigation of `robot_name` and emits `NavigationFailed`.
+    ///
+    /// Fails with `NotNavigating` if there is none.
+    async fn cancel_navigation(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        robot_name: &str,
+    ) -> Result<(), RustyError> {
+        let handle = self
+            .navigations
+            .lock()
+            .await
+            .remove(robot_name)
+            .ok_or_else(|| {
+                RustyError::NotNavigating(format!("Robot {robot_name} is not navigating"))
+            })?;
+        handle.abort();
+        Self::navigation_failed(&emitter, robot_name.to_string(), "cancelled".to_string()).await?;
+        Ok(())
+    }
+
+    /// Asks the service to shut down, which saves the world.
+    async fn quit(&self) {
+        self.shutdown.notify_one();
+    }
+
     #[zbus(property)]
     async fn height(&self) -> u32 {
         self.world.lock().await.height
@@ -55,8 +707,58 @@ impl WorldDbus {
         self.world.lock().await.width
     }
     #[zbus(signal)]
-    async fn robot(emitter: &SignalEmitter<'_>, name: String, x: i32, y: i32) -> zbus::Result<()>;
+    async fn robot(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        x: i32,
+        y: i32,
+        heading: String,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn robot_removed(emitter: &SignalEmitter<'_>, name: String) -> zbus::Result<()>;
 
     #[zbus(signal)]
     async fn tile(emitter: &SignalEmitter<'_>, name: String, x: i32, y: i32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn tick(emitter: &SignalEmitter<'_>, tick: u64) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn command_failed(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        reason: String,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn script_failed(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        reason: String,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn exploration_progress(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        coverage: f64,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn exploration_finished(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        coverage: f64,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn navigation_completed(emitter: &SignalEmitter<'_>, name: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn navigation_failed(
+        emitter: &SignalEmitter<'_>,
+        name: String,
+        reason: String,
+    ) -> zbus::Result<()>;
 }
@@ -0,0 +1,82 @@
+//! Typed D-Bus errors of the world interface.
+
+use crate::moveable::MovementError;
+use crate::planner::PlanError;
+use zbus::DBusError;
+
+/// Errors returned by the world interface, sent as `de.marc.rusty.Error.<Variant>`.
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "de.marc.rusty.Error")]
+pub enum RustyError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// The robot may not move that far at once.
+    TooFar(String),
+    /// The step count is not valid.
+    InvalidStep(String),
+    /// The direction name is not known.
+    InvalidDirection(String),
+    /// A wall is in the way.
+    Blocked(String),
+    /// The move would leave the world.
+    OutOfBounds(String),
+    /// Another robot is in the way.
+    Collision(String),
+    /// The battery is too low for the move.
+    BatteryEmpty(String),
+    /// No robot with that name exists.
+    UnknownRobot(String),
+    /// The robot is not on a charge pad.
+    NoChargePad(String),
+    /// The tile name is not known.
+    InvalidTile(String),
+    /// The program file could not be loaded.
+    InvalidProgram(String),
+    /// The sensor configuration is out of range.
+    InvalidSensor(String),
+    /// No route to the requested goal or goals was found.
+    NoRoute(String),
+    /// The robot has no navigation running that could be cancelled.
+    NotNavigating(String),
+    /// The change would leave the world in a state `World::validate` rejects.
+    InvalidWorld(String),
+}
+
+impl From<PlanError> for RustyError {
+    fn from(error: PlanError) -> Self {
+        match error {
+            PlanError::OutOfBounds(at) => {
+                RustyError::OutOfBounds(format!("{at} lies outside the world"))
+            }
+            PlanError::Blocked(at) => RustyError::Blocked(format!("Wall at {at}")),
+            PlanError::Unreachable => RustyError::NoRoute("Goal is unreachable".to_string()),
+        }
+    }
+}
+
+impl From<MovementError> for RustyError {
+    fn from(error: MovementError) -> Self {
+        match error {
+            MovementError::TooFar => RustyError::TooFar("Too many steps at once".to_string()),
+            MovementError::InvalidStep => {
+                RustyError::InvalidStep("Steps must not be negative".to_string())
+            }
+            MovementError::Blocked { at } => RustyError::Blocked(format!("Wall at {at}")),
+            MovementError::OutOfBounds => {
+                RustyError::OutOfBounds("Move leaves the world".to_string())
+            }
+            MovementError::Collision { with } => {
+                RustyError::Collision(format!("Robot {with} is in the way"))
+            }
+            MovementError::BatteryEmpty => {
+                RustyError::BatteryEmpty("Battery too low for the move".to_string())
+            }
+            MovementError::UnknownRobot(name) => {
+                RustyError::UnknownRobot(format!("Robot {name} not found"))
+            }
+            MovementError::NoChargePad => {
+                RustyError::NoChargePad("Not standing on a charge pad".to_string())
+            }
+        }
+    }
+}
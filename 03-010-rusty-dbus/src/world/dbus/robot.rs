@@ -0,0 +1,129 @@
+//! One D-Bus object per robot below [`ROBOTS_PATH`].
+//!
+//! The objects only hold the robot name and read everything else from the
+//! shared [`World`], so they never go stale. Whoever changes a robot calls
+//! [`notify_changed`] to emit `PropertiesChanged`.
+
+use super::{RustyError, announce_robot, apply_move};
+use crate::world::World;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zbus::object_server::ObjectServer;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{Connection, interface};
+
+/// Parent path of all robot objects.
+pub const ROBOTS_PATH: &str = "/de/marc/rusty/robots";
+
+pub struct RobotDbus {
+    world: Arc<Mutex<World>>,
+    name: String,
+}
+
+/// Object path of robot `name`.
+///
+/// Bytes other than ASCII letters and digits are escaped as `_` and two
+/// hex digits like systemd does, e.g. `a-b` becomes `a_2db`, so different
+/// names never share a path. The empty name becomes `_`.
+pub fn object_path(name: &str) -> zbus::Result<OwnedObjectPath> {
+    let element: String = name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() {
+                char::from(b).to_string()
+            } else {
+                format!("_{b:02x}")
+            }
+        })
+        .collect();
+    let element = if element.is_empty() {
+        "_".into()
+    } else {
+        element
+    };
+    Ok(OwnedObjectPath::try_from(format!(
+        "{ROBOTS_PATH}/{element}"
+    ))?)
+}
+
+/// Exports robot `name` as its own object, which emits `InterfacesAdded`.
+pub async fn export(
+    server: &ObjectServer,
+    world: Arc<Mutex<World>>,
+    name: &str,
+) -> zbus::Result<bool> {
+    let robot = RobotDbus {
+        world,
+        name: name.to_string(),
+    };
+    server.at(object_path(name)?, robot).await
+}
+
+/// Removes the object of robot `name`, which emits `InterfacesRemoved`.
+pub async fn unexport(server: &ObjectServer, name: &str) -> zbus::Result<bool> {
+    server.remove::<RobotDbus, _>(object_path(name)?).await
+}
+
+/// Emits `PropertiesChanged` for all properties of robot `name`.
+pub async fn notify_changed(server: &ObjectServer, name: &str) -> zbus::Result<()> {
+    let iface = server.interface::<_, RobotDbus>(object_path(name)?).await?;
+    let emitter = iface.signal_emitter();
+    let robot = iface.get().await;
+    robot.position_changed(emitter).await?;
+    robot.heading_changed(emitter).await?;
+    robot.state_of_charge_changed(emitter).await
+}
+
+impl RobotDbus {
+    fn not_found(&self) -> zbus::fdo::Error {
+        zbus::fdo::Error::UnknownObject(format!("Robot {} not found", self.name))
+    }
+}
+
+#[interface(name = "de.marc.rusty.Robot")]
+impl RobotDbus {
+    /// Moves the robot, see `MoveRobot` of the world interface.
+    #[zbus(name = "Move")]
+    async fn move_robot(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        direction: &str,
+        steps: i32,
+    ) -> Result<(), RustyError> {
+        let moved = apply_move(&mut *self.world.lock().await, &self.name, direction, steps);
+        announce_robot(connection, &self.world, &self.name).await?;
+        moved
+    }
+
+    #[zbus(property)]
+    async fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[zbus(property)]
+    async fn position(&self) -> zbus::fdo::Result<(i32, i32)> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(&self.name)
+            .ok_or_else(|| self.not_found())?;
+        Ok((robot.position.x, robot.position.y))
+    }
+
+    #[zbus(property)]
+    async fn heading(&self) -> zbus::fdo::Result<String> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(&self.name)
+            .ok_or_else(|| self.not_found())?;
+        Ok(robot.heading.to_string())
+    }
+
+    #[zbus(property)]
+    async fn state_of_charge(&self) -> zbus::fdo::Result<u8> {
+        let world = self.world.lock().await;
+        let robot = world
+            .get_robot(&self.name)
+            .ok_or_else(|| self.not_found())?;
+        Ok(robot.state_of_charge)
+    }
+}
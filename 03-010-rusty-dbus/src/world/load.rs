@@ -0,0 +1,106 @@
+//! Loading saved worlds with errors worth reporting, see [`World::load`].
+
+use crate::world::World;
+use crate::world::migration::FormatError;
+use crate::world::storage::Format;
+use crate::world::validate::Diagnostic;
+use std::fmt;
+use std::path::Path;
+
+/// Why a world file could not be loaded.
+#[derive(Debug)]
+pub enum WorldLoadError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file is no valid world, `line` and `column` are `0` if unknown.
+    Parse {
+        /// 1-based line of the error.
+        line: usize,
+        /// 1-based column of the error.
+        column: usize,
+        /// What is wrong there.
+        message: String,
+    },
+    /// The file's `format_version` is malformed or too new.
+    Version(String),
+    /// The file is a world, but not one that can be simulated.
+    Validation(Vec<Diagnostic>),
+}
+
+impl fmt::Display for WorldLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldLoadError::Io(e) => write!(f, "{e}"),
+            WorldLoadError::Parse {
+                line: 0, message, ..
+            } => write!(f, "{message}"),
+            WorldLoadError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "{message} at line {line} column {column}"),
+            WorldLoadError::Version(message) => write!(f, "{message}"),
+            WorldLoadError::Validation(diagnostics) => {
+                let diagnostics: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "invalid world: {}", diagnostics.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for WorldLoadError {}
+
+impl From<std::io::Error> for WorldLoadError {
+    fn from(error: std::io::Error) -> Self {
+        WorldLoadError::Io(error)
+    }
+}
+
+impl From<FormatError> for WorldLoadError {
+    fn from(error: FormatError) -> Self {
+        match error {
+            FormatError::Json(e) => {
+                let (line, column) = (e.line(), e.column());
+                // the position is kept apart, not in the message
+                let message = e.to_string();
+                let message = message
+                    .strip_suffix(&format!(" at line {line} column {column}"))
+                    .unwrap_or(&message)
+                    .to_string();
+                WorldLoadError::Parse {
+                    line,
+                    column,
+                    message,
+                }
+            }
+            FormatError::Decode {
+                line,
+                column,
+                message,
+            } => WorldLoadError::Parse {
+                line,
+                column,
+                message,
+            },
+            FormatError::InvalidVersion | FormatError::TooNew(_) => {
+                WorldLoadError::Version(error.to_string())
+            }
+        }
+    }
+}
+
+impl World {
+    /// Loads the world file at `path` stored in `format`, upgrading old versions.
+    ///
+    /// # Errors
+    /// Returns a [`WorldLoadError`] if the file cannot be read, is no world
+    /// of a supported version in `format`, or [`World::validate`] finds problems.
+    pub fn load(path: &Path, format: Format) -> Result<World, WorldLoadError> {
+        let world = World::decode(&std::fs::read(path)?, format)?;
+        let diagnostics = world.validate();
+        if !diagnostics.is_empty() {
+            return Err(WorldLoadError::Validation(diagnostics));
+        }
+        Ok(world)
+    }
+}
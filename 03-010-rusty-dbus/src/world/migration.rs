@@ -0,0 +1,160 @@
+//! Versioned world files and the migrations that upgrade old ones.
+//!
+//! Saved worlds carry a `format_version` next to the world's own fields.
+//! Files without one predate versioning and count as version `0`.
+//!
+//! Loading runs the file through [`MIGRATIONS`] on the raw JSON, from its
+//! version up to [`FORMAT_VERSION`], and only then deserializes the
+//! [`World`]. A change to the saved structs that old files cannot follow by
+//! serde defaults alone bumps [`FORMAT_VERSION`] and appends a migration.
+
+use crate::world::World;
+use log::info;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Version of the world files written by this build.
+pub const FORMAT_VERSION: u64 = 1;
+
+/// Name of the version field in world files.
+const VERSION_KEY: &str = "format_version";
+
+/// Upgrades a world from one version to the next, `MIGRATIONS[n]` turns
+/// version `n` into `n + 1`.
+type Migration = fn(&mut Map<String, Value>);
+
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize] = [unversioned];
+
+/// Version `0` to `1`.
+///
+/// Everything added to worlds and robots before versioning started has a
+/// serde default, so the unversioned files only lack the header.
+fn unversioned(_world: &mut Map<String, Value>) {}
+
+/// Errors while reading a world file.
+#[derive(Debug)]
+pub enum FormatError {
+    /// The data is not JSON or does not describe a world.
+    Json(serde_json::Error),
+    /// The data is not valid in its format, see [`crate::world::storage`];
+    /// `line` and `column` are `0` if unknown.
+    Decode {
+        /// 1-based line of the error.
+        line: usize,
+        /// 1-based column of the error.
+        column: usize,
+        /// What is wrong there.
+        message: String,
+    },
+    /// The data is no JSON object or its `format_version` is no number.
+    InvalidVersion,
+    /// The file was written by a newer build, with this version.
+    TooNew(u64),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Json(e) => write!(f, "{e}"),
+            FormatError::Decode {
+                line: 0, message, ..
+            } => write!(f, "{message}"),
+            FormatError::Decode {
+                line,
+                column,
+                message,
+            } => write!(f, "{message} at line {line} column {column}"),
+            FormatError::InvalidVersion => {
+                write!(f, "no world object with a numeric `{VERSION_KEY}`")
+            }
+            FormatError::TooNew(version) => write!(
+                f,
+                "format version {version} is newer than the supported {FORMAT_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(error: serde_json::Error) -> Self {
+        FormatError::Json(error)
+    }
+}
+
+/// Upgrades a parsed world file to [`FORMAT_VERSION`] in place.
+///
+/// Returns the version the file had.
+///
+/// # Errors
+/// Returns [`FormatError::InvalidVersion`] or [`FormatError::TooNew`] if the
+/// file cannot be upgraded.
+pub fn upgrade(data: &mut Value) -> Result<u64, FormatError> {
+    let world = data.as_object_mut().ok_or(FormatError::InvalidVersion)?;
+    let version = match world.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version.as_u64().ok_or(FormatError::InvalidVersion)?,
+    };
+    if version > FORMAT_VERSION {
+        return Err(FormatError::TooNew(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(world);
+    }
+    world.insert(VERSION_KEY.to_string(), FORMAT_VERSION.into());
+    Ok(version)
+}
+
+/// A world with the version header, as written to files.
+#[derive(Serialize)]
+pub(crate) struct Versioned<'a> {
+    format_version: u64,
+    #[serde(flatten)]
+    world: &'a World,
+}
+
+impl<'a> Versioned<'a> {
+    /// `world` with the current [`FORMAT_VERSION`].
+    pub(crate) fn new(world: &'a World) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            world,
+        }
+    }
+}
+
+/// Upgrades a world file read into the JSON data model and deserializes it.
+pub(crate) fn from_value(mut value: Value) -> Result<World, FormatError> {
+    let version = upgrade(&mut value)?;
+    if version < FORMAT_VERSION {
+        info!("Upgraded world from format version {version} to {FORMAT_VERSION}");
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+impl World {
+    /// Reads a world file of any supported version, upgrading old ones.
+    ///
+    /// Errors in the content of upgraded files have no line and column.
+    ///
+    /// # Errors
+    /// Returns a [`FormatError`] if the data is no world or too new.
+    pub fn from_json(data: &str) -> Result<World, FormatError> {
+        let value: Value = serde_json::from_str(data)?;
+        if value.get(VERSION_KEY).and_then(Value::as_u64) == Some(FORMAT_VERSION) {
+            // from the text again, errors keep their line and column
+            return Ok(serde_json::from_str(data)?);
+        }
+        from_value(value)
+    }
+
+    /// Writes the world as pretty printed JSON with the current `format_version`.
+    ///
+    /// # Errors
+    /// Returns the serializer's error, which only happens for broken worlds.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&Versioned::new(self))
+    }
+}
@@ -0,0 +1,161 @@
+//! Formats world files can be stored in, see [`Format`].
+//!
+//! Whatever the format, a file holds the same fields as the JSON one,
+//! including `format_version`. Files of every format are written from and
+//! read into the JSON data model, so enums look alike everywhere and the
+//! migrations of [`crate::world::migration`] upgrade all of them alike.
+
+use crate::world::World;
+use crate::world::migration::{self, FormatError, Versioned};
+use serde_json::Value;
+use std::io;
+use std::path::Path;
+
+/// Serialization format of a world file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Pretty printed JSON, `.json`.
+    #[default]
+    Json,
+    /// TOML, `.toml`.
+    Toml,
+    /// Rusty Object Notation, `.ron`.
+    Ron,
+    /// Compact binary MessagePack, `.msgpack` or `.mpk`.
+    #[value(name = "msgpack")]
+    MessagePack,
+    /// Compact binary CBOR, `.cbor`.
+    Cbor,
+}
+
+impl Format {
+    /// Format belonging to the extension of `path`, if it is a known one.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        let format = match extension.as_str() {
+            "json" => Format::Json,
+            "toml" => Format::Toml,
+            "ron" => Format::Ron,
+            "msgpack" | "mpk" => Format::MessagePack,
+            "cbor" => Format::Cbor,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    /// `format` if given, else the format of the extension of `path`, else JSON.
+    pub fn choose(format: Option<Format>, path: &Path) -> Format {
+        format
+            .or_else(|| Format::from_extension(path))
+            .unwrap_or_default()
+    }
+
+    /// Parses `data` into the JSON data model.
+    ///
+    /// Text formats report where a syntax error is; errors of binary
+    /// formats have no line and column.
+    pub(crate) fn parse(self, data: &[u8]) -> Result<Value, FormatError> {
+        let text = || {
+            std::str::from_utf8(data).map_err(|e| FormatError::Decode {
+                line: 0,
+                column: 0,
+                message: e.to_string(),
+            })
+        };
+        match self {
+            Format::Json => Ok(serde_json::from_slice(data)?),
+            Format::Toml => {
+                let text = text()?;
+                toml::from_str(text).map_err(|e| {
+                    let (line, column) = e
+                        .span()
+                        .map(|span| line_and_column(text, span.start))
+                        .unwrap_or_default();
+                    FormatError::Decode {
+                        line,
+                        column,
+                        message: e.message().to_string(),
+                    }
+                })
+            }
+            Format::Ron => ron::from_str(text()?).map_err(|e| FormatError::Decode {
+                line: e.span.start.line,
+                column: e.span.start.col,
+                message: e.code.to_string(),
+            }),
+            Format::MessagePack => rmp_serde::from_slice(data).map_err(decode_error),
+            Format::Cbor => ciborium::from_reader(data).map_err(decode_error),
+        }
+    }
+}
+
+/// An error of a binary format, which has no position.
+fn decode_error(error: impl std::error::Error) -> FormatError {
+    FormatError::Decode {
+        line: 0,
+        column: 0,
+        message: error.to_string(),
+    }
+}
+
+/// A world the format cannot hold.
+fn invalid_data(error: impl std::error::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// 1-based line and column of the byte `offset` in `text`.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+impl World {
+    /// Reads a world stored in `format`, upgrading old versions.
+    ///
+    /// # Errors
+    /// Returns a [`FormatError`] if the data is no world of that format
+    /// or too new.
+    pub fn decode(data: &[u8], format: Format) -> Result<World, FormatError> {
+        if format == Format::Json {
+            let text = std::str::from_utf8(data).map_err(decode_error)?;
+            return World::from_json(text);
+        }
+        migration::from_value(format.parse(data)?)
+    }
+
+    /// Writes the world in `format` with the current `format_version`.
+    ///
+    /// # Errors
+    /// Returns an [`io::ErrorKind::InvalidData`] error if the format cannot
+    /// hold the world.
+    pub fn encode(&self, format: Format) -> io::Result<Vec<u8>> {
+        let value = || serde_json::to_value(Versioned::new(self));
+        match format {
+            Format::Json => Ok(self.to_json()?.into_bytes()),
+            Format::Toml => toml::to_string_pretty(&value()?)
+                .map(String::into_bytes)
+                .map_err(invalid_data),
+            Format::Ron => ron::ser::to_string_pretty(&value()?, ron::ser::PrettyConfig::default())
+                .map(String::into_bytes)
+                .map_err(invalid_data),
+            Format::MessagePack => rmp_serde::to_vec(&value()?).map_err(invalid_data),
+            Format::Cbor => {
+                let mut data = Vec::new();
+                ciborium::into_writer(&value()?, &mut data).map_err(invalid_data)?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Writes the world to `path` in `format`.
+    ///
+    /// # Errors
+    /// Returns the error of [`World::encode`] or of writing the file.
+    pub fn save(&self, path: &Path, format: Format) -> io::Result<()> {
+        std::fs::write(path, self.encode(format)?)
+    }
+}
@@ -0,0 +1,605 @@
+use super::*;
+use crate::heading::Heading;
+use crate::mapping::{Belief, OccupancyGrid};
+use crate::sensor::Lidar;
+use crate::world::ascii::{AsciiError, AsciiErrorKind};
+use crate::world::load::WorldLoadError;
+use crate::world::migration::{self, FORMAT_VERSION, FormatError};
+use crate::world::storage::Format;
+use crate::world::validate::Diagnostic;
+
+fn world_with_wall() -> World {
+    let mut world = World::new(10, 10);
+    world.add_tile(Position::new(0, 3), Tile::Wall);
+    world.add_robot_new(String::from("rusty"));
+    world
+}
+
+#[test]
+fn move_robot_free() {
+    let mut world = world_with_wall();
+    assert!(
+        world
+            .move_robot("rusty", Direction::Forward { step: 2 })
+            .is_ok()
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 2)));
+}
+
+#[test]
+fn move_robot_blocked_stops_before_wall() {
+    let mut world = world_with_wall();
+    assert_eq!(
+        world.move_robot("rusty", Direction::Forward { step: 3 }),
+        Err(MovementError::Blocked {
+            at: Position::new(0, 3)
+        })
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 2)));
+}
+
+#[test]
+fn move_robot_blocked_reject_whole() {
+    let mut world = world_with_wall();
+    world.set_move_policy(MovePolicy::RejectWhole);
+    assert!(
+        world
+            .move_robot("rusty", Direction::Forward { step: 3 })
+            .is_err()
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 0)));
+}
+
+#[test]
+fn move_robot_out_of_bounds() {
+    let mut world = world_with_wall();
+    assert_eq!(
+        world.move_robot("rusty", Direction::Left),
+        Err(MovementError::OutOfBounds)
+    );
+    assert_eq!(
+        world.move_robot("rusty", Direction::Backwards),
+        Err(MovementError::OutOfBounds)
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 0)));
+}
+
+#[test]
+fn move_robot_unknown() {
+    let mut world = world_with_wall();
+    assert_eq!(
+        world.move_robot("karl", Direction::Right),
+        Err(MovementError::UnknownRobot(String::from("karl")))
+    );
+}
+
+#[test]
+fn move_robot_collision() {
+    let mut world = world_with_wall();
+    world.add_robot_new(String::from("karl"));
+    assert!(world.move_robot("karl", Direction::Right).is_ok());
+    assert_eq!(
+        world.move_robot("rusty", Direction::Right),
+        Err(MovementError::Collision {
+            with: String::from("karl")
+        })
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 0)));
+}
+
+#[test]
+fn move_robot_ghost_overlaps() {
+    let mut world = world_with_wall();
+    world.add_robot_new(String::from("karl"));
+    assert!(world.move_robot("karl", Direction::Right).is_ok());
+    assert!(world.set_ghost("rusty", true).is_ok());
+    assert!(world.move_robot("rusty", Direction::Right).is_ok());
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(1, 0)));
+    // a solid robot may also step onto a ghost
+    assert!(world.move_robot("karl", Direction::Left).is_ok());
+}
+
+#[test]
+fn move_robot_turn_then_forward() {
+    let mut world = world_with_wall();
+    assert!(world.move_robot("rusty", Direction::TurnRight).is_ok());
+    assert!(
+        world
+            .move_robot("rusty", Direction::Forward { step: 3 })
+            .is_ok()
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(3, 0)));
+}
+
+#[test]
+fn remove_robot_frees_cell() {
+    let mut world = world_with_wall();
+    world.add_robot_new(String::from("karl"));
+    assert!(world.move_robot("karl", Direction::Right).is_ok());
+    assert!(world.remove_robot("karl").is_some());
+    assert!(world.remove_robot("karl").is_none());
+    assert!(world.move_robot("rusty", Direction::Right).is_ok());
+}
+
+#[test]
+fn move_robot_battery_empty() {
+    let mut world = world_with_wall();
+    world.robots[0].state_of_charge = 1;
+    assert_eq!(
+        world.move_robot("rusty", Direction::Forward { step: 2 }),
+        Err(MovementError::BatteryEmpty)
+    );
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 1)));
+    assert_eq!(world.robots[0].state_of_charge, 0);
+}
+
+#[test]
+fn step_recharges_on_charge_pad() {
+    let mut world = world_with_wall();
+    world.add_tile(Position::new(1, 0), Tile::ChargePad);
+    world.add_robot_new(String::from("karl"));
+    world.robots[0].state_of_charge = 10;
+    world.robots[1].state_of_charge = 10;
+    assert!(world.move_robot("rusty", Direction::Right).is_ok());
+    assert_eq!(
+        world.step(1),
+        vec![WorldEvent::RobotCharged {
+            name: String::from("rusty"),
+            state_of_charge: 9 + CHARGE_PER_TICK,
+        }]
+    );
+    assert_eq!(world.robots[0].state_of_charge, 9 + CHARGE_PER_TICK);
+    assert_eq!(world.robots[1].state_of_charge, 10);
+}
+
+#[test]
+fn step_works_off_commands_in_order() {
+    let mut world = world_with_wall();
+    let program = Program {
+        commands: vec![
+            Command::TurnRight,
+            Command::Wait { ticks: 2 },
+            Command::Forward { step: 2 },
+        ],
+    };
+    assert!(world.load_program("rusty", program).is_ok());
+    world.step(1);
+    assert_eq!(world.robots[0].heading, crate::heading::Heading::East);
+    world.step(2);
+    assert_eq!(world.robots[0].commands.front(), Some(&Command::Wait { ticks: 1 }));
+    world.step(3);
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 0)));
+    world.step(4);
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(2, 0)));
+    assert!(world.robots[0].commands.is_empty());
+}
+
+#[test]
+fn step_charges_until_full() {
+    let mut world = world_with_wall();
+    world.add_tile(Position::new(0, 0), Tile::ChargePad);
+    world.robots[0].state_of_charge = FULL_CHARGE - CHARGE_PER_TICK;
+    assert!(world.queue_command("rusty", Command::ChargeUntilFull).is_ok());
+    assert!(world.queue_command("rusty", Command::TurnLeft).is_ok());
+    world.step(1);
+    assert_eq!(world.robots[0].state_of_charge, FULL_CHARGE);
+    assert_eq!(world.robots[0].commands.len(), 2);
+    world.step(2);
+    assert_eq!(world.robots[0].commands, vec![Command::TurnLeft]);
+
+    // charging away from a pad fails right away
+    world.robots[0].position = Position::new(5, 5);
+    world.robots[0].state_of_charge = 0;
+    assert!(world.clear_commands("rusty").is_ok_and(|cleared| cleared == 1));
+    assert!(world.queue_command("rusty", Command::ChargeUntilFull).is_ok());
+    assert_eq!(
+        world.step(3),
+        vec![WorldEvent::CommandFailed {
+            name: String::from("rusty"),
+            error: MovementError::NoChargePad,
+        }]
+    );
+    assert!(world.robots[0].commands.is_empty());
+}
+
+#[test]
+fn step_updates_belief_maps() {
+    let mut world = world_with_wall();
+    assert!(world.robots[0].belief.is_none());
+    world.step(1);
+    let belief = world.robots[0].belief.as_ref();
+    assert_eq!(
+        belief.map(|belief| belief.belief(&Position::new(0, 2))),
+        Some(Belief::Free)
+    );
+    assert_eq!(
+        belief.map(|belief| belief.belief(&Position::new(0, 3))),
+        Some(Belief::Occupied)
+    );
+    assert_eq!(
+        belief.map(|belief| belief.belief(&Position::new(0, 4))),
+        Some(Belief::Unknown)
+    );
+}
+
+#[test]
+fn from_ascii_reads_arena() -> Result<(), AsciiError> {
+    let world = World::from_ascii(include_str!("../../arenas/two_rooms.txt"))?;
+    assert_eq!((world.width(), world.height()), (10, 6));
+    assert_eq!(world.tile(&Position::new(0, 0)), Some(&Tile::Wall));
+    assert_eq!(
+        world.tile(&Position::new(5, 3)),
+        Some(&Tile::Door {
+            open: false,
+            period: 6
+        })
+    );
+    assert_eq!(world.tile(&Position::new(8, 1)), Some(&Tile::ChargePad));
+    assert_eq!(world.tile(&Position::new(2, 2)), None);
+    let robots: Vec<_> = world
+        .robots()
+        .iter()
+        .map(|robot| (robot.name.as_str(), robot.position.clone(), robot.heading))
+        .collect();
+    assert_eq!(
+        robots,
+        [
+            ("rusty", Position::new(1, 4), Heading::East),
+            ("karl", Position::new(8, 1), Heading::South)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn to_ascii_round_trips() -> Result<(), AsciiError> {
+    let arena = include_str!("../../arenas/two_rooms.txt");
+    assert_eq!(World::from_ascii(arena)?.to_ascii(), arena);
+
+    let mut world = world_with_wall();
+    world.add_tile(
+        Position::new(4, 4),
+        Tile::Door {
+            open: true,
+            period: 3,
+        },
+    );
+    world.add_robot_existing(Robot::new(String::from("karl")));
+    let map = world.to_ascii();
+    // karl shares rusty's cell and is left out
+    assert_eq!(World::from_ascii(&map)?.robots().len(), 1);
+    assert_eq!(World::from_ascii(&map)?.to_ascii(), map);
+    Ok(())
+}
+
+#[test]
+fn from_ascii_points_at_errors() {
+    let error = |map: &str| {
+        World::from_ascii(map)
+            .err()
+            .map(|e| (e.line, e.column, e.kind))
+    };
+    assert_eq!(
+        error("WWW\nW?W\n"),
+        Some((2, 2, AsciiErrorKind::UnknownCharacter('?')))
+    );
+    assert_eq!(
+        error("r..\n\nr = robot rusty up\n"),
+        Some((
+            3,
+            1,
+            AsciiErrorKind::InvalidLegend(String::from("r = robot rusty up"))
+        ))
+    );
+    assert_eq!(
+        error("r.r\n\nr = robot rusty\n"),
+        Some((1, 3, AsciiErrorKind::RobotPlacedTwice('r')))
+    );
+    assert_eq!(
+        error("r..\n\nr = robot rusty\nk = robot karl\n"),
+        Some((4, 1, AsciiErrorKind::MissingRobot(String::from("karl"))))
+    );
+    assert_eq!(
+        error("W..\n\nW = door open 2\n"),
+        Some((3, 1, AsciiErrorKind::DuplicateSymbol('W')))
+    );
+}
+
+#[test]
+fn from_json_upgrades_unversioned_saves() -> Result<(), FormatError> {
+    // the save shipped before worlds had a format version
+    let world = World::from_json(include_str!("../../world.json"))?;
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(1, 1)));
+    assert_eq!(world.robots[0].heading, Heading::North);
+    assert_eq!(world.robots[0].state_of_charge, FULL_CHARGE);
+
+    let data = world.to_json()?;
+    assert!(data.starts_with("{\n  \"format_version\": 1,"));
+    let mut value: serde_json::Value = serde_json::from_str(&data)?;
+    assert_eq!(migration::upgrade(&mut value)?, FORMAT_VERSION);
+    assert_eq!(World::from_json(&data)?.to_json()?, data);
+    Ok(())
+}
+
+#[test]
+fn from_json_rejects_unknown_versions() {
+    assert!(matches!(
+        World::from_json(r#"{"format_version": 99, "height": 1, "width": 1}"#),
+        Err(FormatError::TooNew(99))
+    ));
+    assert!(matches!(
+        World::from_json(r#"{"format_version": "one"}"#),
+        Err(FormatError::InvalidVersion)
+    ));
+    assert!(matches!(
+        World::from_json(r#"{"format_version": 1, "height": 1}"#),
+        Err(FormatError::Json(_))
+    ));
+}
+
+/// Writes `data` to a world file unique to the calling test.
+fn world_file(test: &str, data: &str) -> std::io::Result<std::path::PathBuf> {
+    let path = std::env::temp_dir().join(format!("rusty-{}-{test}.json", std::process::id()));
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+#[test]
+fn load_reports_what_is_wrong() -> std::io::Result<()> {
+    let missing = std::env::temp_dir().join("rusty-no-such-world.json");
+    assert!(matches!(
+        World::load(&missing, Format::Json),
+        Err(WorldLoadError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+
+    let path = world_file("syntax", "{\n  \"height\": 2,\n  \"width\": }\n")?;
+    assert!(matches!(
+        World::load(&path, Format::Json),
+        Err(WorldLoadError::Parse { line: 3, column: 12, .. })
+    ));
+
+    let path = world_file(
+        "type",
+        "{\n  \"format_version\": 1,\n  \"height\": \"high\",\n  \"width\": 2\n}",
+    )?;
+    let error = World::load(&path, Format::Json).err().map(|e| e.to_string());
+    assert_eq!(
+        error.as_deref(),
+        Some("invalid type: string \"high\", expected u32 at line 3 column 18")
+    );
+
+    let mut world = world_with_wall();
+    world.add_robot_new(String::from("rusty"));
+    let path = world_file("twice", &world.to_json()?)?;
+    assert!(matches!(
+        World::load(&path, Format::Json),
+        Err(WorldLoadError::Validation(diagnostics)) if diagnostics.len() == 2
+    ));
+
+    let path = world_file("newer", r#"{"format_version": 2}"#)?;
+    assert!(matches!(
+        World::load(&path, Format::Json),
+        Err(WorldLoadError::Version(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn validate_finds_broken_worlds() {
+    let mut world = world_with_wall();
+    assert_eq!(world.validate(), []);
+
+    let mut robot = Robot::new(String::from("karl"));
+    robot.position = Position::new(0, 3);
+    world.add_robot_existing(robot);
+    let mut robot = Robot::new(String::from("rusty"));
+    robot.position = Position::new(10, 0);
+    world.add_robot_existing(robot);
+    let mut robot = Robot::new(String::from("casper"));
+    robot.ghost = true;
+    robot.lidar.beams = 4_000_000_000;
+    robot.belief = Some(OccupancyGrid::new(3, 2));
+    world.add_robot_existing(robot);
+    world.add_robot_new(String::from("boris"));
+    world.add_tile(Position::new(-1, 2), Tile::ChargePad);
+    assert_eq!(
+        world.validate(),
+        [
+            Diagnostic::RobotInWall {
+                name: String::from("karl"),
+                position: Position::new(0, 3)
+            },
+            Diagnostic::DuplicateRobot {
+                name: String::from("rusty")
+            },
+            Diagnostic::RobotOutOfBounds {
+                name: String::from("rusty"),
+                position: Position::new(10, 0)
+            },
+            Diagnostic::InvalidLidar {
+                name: String::from("casper"),
+                lidar: Lidar {
+                    beams: 4_000_000_000,
+                    range: Lidar::default().range
+                }
+            },
+            Diagnostic::BeliefSizeMismatch {
+                name: String::from("casper"),
+                width: 3,
+                height: 2
+            },
+            Diagnostic::RobotsOverlap {
+                first: String::from("rusty"),
+                second: String::from("boris"),
+                position: Position::new(0, 0)
+            },
+            Diagnostic::TileOutOfBounds {
+                position: Position::new(-1, 2),
+                tile: Tile::ChargePad
+            },
+        ]
+    );
+    assert_eq!(world.free_cell(), Some(Position::new(1, 0)));
+
+    // the same checks one robot or cell at a time
+    assert_eq!(
+        world.robots.last().map(|boris| world.validate_robot(boris)),
+        Some(vec![Diagnostic::RobotsOverlap {
+            first: String::from("rusty"),
+            second: String::from("boris"),
+            position: Position::new(0, 0)
+        }])
+    );
+    let rusty = Robot::new(String::from("rusty"));
+    assert_eq!(
+        world.validate_robot(&rusty),
+        [
+            Diagnostic::DuplicateRobot {
+                name: String::from("rusty")
+            },
+            Diagnostic::RobotsOverlap {
+                first: String::from("rusty"),
+                second: String::from("rusty"),
+                position: Position::new(0, 0)
+            }
+        ]
+    );
+    assert_eq!(
+        world.validate_cell(&Position::new(0, 3)),
+        [Diagnostic::RobotInWall {
+            name: String::from("karl"),
+            position: Position::new(0, 3)
+        }]
+    );
+    assert_eq!(
+        world.validate_cell(&Position::new(-1, 2)),
+        [Diagnostic::TileOutOfBounds {
+            position: Position::new(-1, 2),
+            tile: Tile::ChargePad
+        }]
+    );
+    assert_eq!(world.validate_cell(&Position::new(1, 0)), []);
+}
+
+#[test]
+fn robot_object_paths_are_distinct() -> zbus::Result<()> {
+    let paths = ["a-b", "a_b", "a b", "a_2db", "", "Rusty2"]
+        .map(dbus::robot::object_path)
+        .into_iter()
+        .collect::<zbus::Result<HashSet<_>>>()?;
+    assert_eq!(paths.len(), 6);
+    assert_eq!(
+        dbus::robot::object_path("a-b")?.as_str(),
+        "/de/marc/rusty/robots/a_2db"
+    );
+    Ok(())
+}
+
+#[test]
+fn dbus_changes_that_break_the_world_are_rejected() {
+    let mut world = world_with_wall();
+    world.add_tile(Position::new(0, 0), Tile::ChargePad);
+    let rejected = dbus::place_tile(&mut world, Position::new(0, 0), Tile::Wall);
+    assert!(matches!(rejected, Err(dbus::RustyError::InvalidWorld(_))));
+    assert_eq!(world.tile(&Position::new(0, 0)), Some(&Tile::ChargePad));
+    let rejected = dbus::place_tile(&mut world, Position::new(-1, 0), Tile::ChargePad);
+    assert!(matches!(rejected, Err(dbus::RustyError::InvalidWorld(_))));
+    assert_eq!(world.tile(&Position::new(-1, 0)), None);
+    let rejected = dbus::place_robot(&mut world, "rusty");
+    assert!(matches!(rejected, Err(dbus::RustyError::InvalidWorld(_))));
+    assert_eq!(world.robots.len(), 1);
+    assert!(dbus::place_robot(&mut world, "karl").is_ok());
+    assert_eq!(world.get_robot_position("karl"), Some(Position::new(1, 0)));
+    assert!(world.remove_robot("karl").is_some());
+
+    // a move stopped by the wall keeps the steps made before it
+    let moved = dbus::apply_move(&mut world, "rusty", "forward", 3);
+    assert!(matches!(moved, Err(dbus::RustyError::Blocked(_))));
+    assert_eq!(world.get_robot_position("rusty"), Some(Position::new(0, 2)));
+}
+
+#[test]
+fn every_format_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let mut world = world_with_wall();
+    world.add_tile(
+        Position::new(3, 3),
+        Tile::Door {
+            open: false,
+            period: 4,
+        },
+    );
+    world.add_tile(Position::new(5, 0), Tile::ChargePad);
+    assert!(
+        world
+            .move_robot("rusty", Direction::Forward { step: 2 })
+            .is_ok()
+    );
+    assert!(world.queue_command("rusty", Command::Wait { ticks: 3 }).is_ok());
+    assert!(world.start_exploration("rusty").is_ok());
+    world.step(1);
+    let json = world.to_json()?;
+    // tiles and visited cells come from hash maps and sets in any order
+    fn canonical(world: &World) -> serde_json::Result<String> {
+        fn sort(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Array(items) => {
+                    items.iter_mut().for_each(sort);
+                    items.sort_by_key(|item| item.to_string());
+                }
+                serde_json::Value::Object(fields) => fields.values_mut().for_each(sort),
+                _ => {}
+            }
+        }
+        let mut value = serde_json::from_str(&world.to_json()?)?;
+        sort(&mut value);
+        Ok(value.to_string())
+    }
+    for format in [
+        Format::Json,
+        Format::Toml,
+        Format::Ron,
+        Format::MessagePack,
+        Format::Cbor,
+    ] {
+        let data = world.encode(format)?;
+        assert_eq!(
+            canonical(&World::decode(&data, format)?)?,
+            canonical(&world)?,
+            "{format:?}"
+        );
+    }
+    assert!(world.encode(Format::Cbor)?.len() < json.len() / 2);
+    Ok(())
+}
+
+#[test]
+fn formats_follow_extensions() {
+    let format = |path: &str| Format::from_extension(std::path::Path::new(path));
+    assert_eq!(format("world.json"), Some(Format::Json));
+    assert_eq!(format("arena.TOML"), Some(Format::Toml));
+    assert_eq!(format("arena.ron"), Some(Format::Ron));
+    assert_eq!(format("big.mpk"), Some(Format::MessagePack));
+    assert_eq!(format("big.cbor"), Some(Format::Cbor));
+    assert_eq!(format("world"), None);
+    assert_eq!(
+        Format::choose(Some(Format::Ron), std::path::Path::new("world.json")),
+        Format::Ron
+    );
+    assert_eq!(
+        Format::choose(None, std::path::Path::new("world.bin")),
+        Format::Json
+    );
+}
+
+#[test]
+fn decode_points_at_errors() {
+    let error = |data: &str, format| {
+        World::decode(data.as_bytes(), format).err().map(|e| match WorldLoadError::from(e) {
+            WorldLoadError::Parse { line, column, .. } => (line, column),
+            _ => (0, 0),
+        })
+    };
+    assert_eq!(error("height = 2\nwidth = = 3\n", Format::Toml), Some((2, 9)));
+    assert_eq!(error("{\"height\": 2,\n  width: 3}", Format::Ron), Some((2, 3)));
+    assert_eq!(error("\u{c1}", Format::Cbor), Some((0, 0)));
+}
@@ -0,0 +1,240 @@
+//! Semantic checks of worlds, see [`World::validate`].
+
+use crate::position::Position;
+use crate::robot::Robot;
+use crate::sensor::{Lidar, MAX_BEAMS, MAX_RANGE};
+use crate::world::{Tile, World};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A problem [`World::validate`] found in a world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// A robot stands outside the world.
+    RobotOutOfBounds {
+        /// Name of the robot.
+        name: String,
+        /// Where it stands.
+        position: Position,
+    },
+    /// More than one robot has this name.
+    DuplicateRobot {
+        /// The shared name.
+        name: String,
+    },
+    /// A robot stands on a wall or a closed door.
+    RobotInWall {
+        /// Name of the robot.
+        name: String,
+        /// Where it stands.
+        position: Position,
+    },
+    /// Two robots that are no ghosts share a cell.
+    RobotsOverlap {
+        /// Name of the robot listed first.
+        first: String,
+        /// Name of the robot listed later.
+        second: String,
+        /// The shared cell.
+        position: Position,
+    },
+    /// A robot's lidar has settings `ConfigureLidar` would reject, see
+    /// [`Lidar::is_valid`].
+    InvalidLidar {
+        /// Name of the robot.
+        name: String,
+        /// The lidar settings.
+        lidar: Lidar,
+    },
+    /// A robot's belief map has another size than the world.
+    ///
+    /// Maps whose log-odds do not fit their own size already fail to load.
+    BeliefSizeMismatch {
+        /// Name of the robot.
+        name: String,
+        /// Columns of the belief map.
+        width: u32,
+        /// Rows of the belief map.
+        height: u32,
+    },
+    /// A tile lies outside the world, e.g. at negative coordinates.
+    TileOutOfBounds {
+        /// Where the tile lies.
+        position: Position,
+        /// The tile.
+        tile: Tile,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::RobotOutOfBounds { name, position } => {
+                write!(f, "robot {name} at {position} is outside the world")
+            }
+            Diagnostic::DuplicateRobot { name } => write!(f, "robot {name} exists more than once"),
+            Diagnostic::RobotInWall { name, position } => {
+                write!(f, "robot {name} at {position} stands inside a wall")
+            }
+            Diagnostic::RobotsOverlap {
+                first,
+                second,
+                position,
+            } => write!(f, "robots {first} and {second} share {position}"),
+            Diagnostic::InvalidLidar { name, lidar } => write!(
+                f,
+                "lidar of robot {name} has {} beams and range {}, \
+                 allowed are 1 to {MAX_BEAMS} beams and a range up to {MAX_RANGE}",
+                lidar.beams, lidar.range
+            ),
+            Diagnostic::BeliefSizeMismatch {
+                name,
+                width,
+                height,
+            } => write!(
+                f,
+                "belief map of robot {name} has {width}x{height} cells, unlike the world"
+            ),
+            Diagnostic::TileOutOfBounds { position, tile } => {
+                write!(f, "tile {tile:?} at {position} is outside the world")
+            }
+        }
+    }
+}
+
+impl World {
+    /// Checks the world for states the simulation cannot handle.
+    ///
+    /// Robots come first in their order, then tiles from south to north and
+    /// west to east. An empty list means the world is fine.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut names = HashSet::new();
+        let mut reported = HashSet::new();
+        let mut solid: HashMap<&Position, &str> = HashMap::new();
+        for robot in &self.robots {
+            let (name, position) = (&robot.name, &robot.position);
+            if !names.insert(name) && reported.insert(name) {
+                diagnostics.push(Diagnostic::DuplicateRobot { name: name.clone() });
+            }
+            self.check_robot(robot, &mut diagnostics);
+            if robot.ghost {
+                continue;
+            }
+            match solid.entry(position) {
+                Entry::Occupied(first) => diagnostics.push(Diagnostic::RobotsOverlap {
+                    first: first.get().to_string(),
+                    second: name.clone(),
+                    position: position.clone(),
+                }),
+                Entry::Vacant(cell) => {
+                    cell.insert(name);
+                }
+            }
+        }
+
+        let mut outside: Vec<(&Position, &Tile)> = self
+            .tiles
+            .iter()
+            .filter(|(position, _)| !self.in_bounds(position))
+            .collect();
+        outside.sort_by_key(|(position, _)| (position.y, position.x));
+        diagnostics.extend(outside.into_iter().map(|(position, tile)| {
+            Diagnostic::TileOutOfBounds {
+                position: position.clone(),
+                tile: tile.clone(),
+            }
+        }));
+        diagnostics
+    }
+
+    /// Checks `robot` like [`World::validate`] checks the robots of the world.
+    ///
+    /// `robot` may be one of them or a robot about to be added, it counts as
+    /// listed last. Costs one pass over the robots, not over the whole world.
+    pub fn validate_robot(&self, robot: &Robot) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut others = self
+            .robots
+            .iter()
+            .filter(|other| !std::ptr::eq(*other, robot));
+        if others.clone().any(|other| other.name == robot.name) {
+            diagnostics.push(Diagnostic::DuplicateRobot {
+                name: robot.name.clone(),
+            });
+        }
+        self.check_robot(robot, &mut diagnostics);
+        if !robot.ghost
+            && let Some(other) =
+                others.find(|other| !other.ghost && other.position == robot.position)
+        {
+            diagnostics.push(Diagnostic::RobotsOverlap {
+                first: other.name.clone(),
+                second: robot.name.clone(),
+                position: robot.position.clone(),
+            });
+        }
+        diagnostics
+    }
+
+    /// Checks the cell at `position` like [`World::validate`] does: robots
+    /// standing in a wall there, then a tile outside the world.
+    pub fn validate_cell(&self, position: &Position) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.in_bounds(position) && self.is_blocked(position) {
+            diagnostics.extend(
+                self.robots
+                    .iter()
+                    .filter(|robot| robot.position == *position)
+                    .map(|robot| Diagnostic::RobotInWall {
+                        name: robot.name.clone(),
+                        position: position.clone(),
+                    }),
+            );
+        }
+        if let Some(tile) = self
+            .tiles
+            .get(position)
+            .filter(|_| !self.in_bounds(position))
+        {
+            diagnostics.push(Diagnostic::TileOutOfBounds {
+                position: position.clone(),
+                tile: tile.clone(),
+            });
+        }
+        diagnostics
+    }
+
+    /// Adds what is wrong with `robot` on its own: where it stands, its lidar
+    /// and its belief map.
+    fn check_robot(&self, robot: &Robot, diagnostics: &mut Vec<Diagnostic>) {
+        let (name, position) = (&robot.name, &robot.position);
+        if !self.in_bounds(position) {
+            diagnostics.push(Diagnostic::RobotOutOfBounds {
+                name: name.clone(),
+                position: position.clone(),
+            });
+        } else if self.is_blocked(position) {
+            diagnostics.push(Diagnostic::RobotInWall {
+                name: name.clone(),
+                position: position.clone(),
+            });
+        }
+        if !robot.lidar.is_valid() {
+            diagnostics.push(Diagnostic::InvalidLidar {
+                name: name.clone(),
+                lidar: robot.lidar,
+            });
+        }
+        if let Some(belief) = &robot.belief
+            && (belief.width(), belief.height()) != (self.width, self.height)
+        {
+            diagnostics.push(Diagnostic::BeliefSizeMismatch {
+                name: name.clone(),
+                width: belief.width(),
+                height: belief.height(),
+            });
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use clap::Parser;
+
+/// Terminal client for the rusty-the-robot D-Bus service.
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Watch and steer robots of the rusty-the-robot service."
+)]
+pub struct Cli {
+    /// Name of the robot steered with the arrow keys.
+    #[arg(short, long, default_value = "rusty")]
+    pub name: String,
+
+    /// Do not connect to the service, simulate a local world instead.
+    #[arg(long)]
+    pub offline: bool,
+}
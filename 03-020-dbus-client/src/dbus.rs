@@ -0,0 +1,36 @@
+use zbus::proxy;
+
+/// Proxy for the world interface of the `de.marc.rusty` service.
+#[proxy(
+    interface = "org.example.something",
+    default_service = "de.marc.rusty",
+    default_path = "/"
+)]
+pub trait Rusty {
+    fn add_robot(&self, robot_name: &str) -> zbus::Result<()>;
+
+    fn get_robot(&self, robot_name: &str) -> zbus::Result<(i32, i32)>;
+
+    fn list_robots(&self) -> zbus::Result<Vec<(String, i32, i32)>>;
+
+    fn get_tiles(&self) -> zbus::Result<Vec<(String, i32, i32)>>;
+
+    fn move_robot(&self, robot_name: &str, direction: &str, steps: i32) -> zbus::Result<()>;
+
+    fn get_belief_map(&self, robot_name: &str) -> zbus::Result<Vec<(i32, i32, String)>>;
+
+    #[zbus(property)]
+    fn height(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn width(&self) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn robot(&self, name: String, x: i32, y: i32, heading: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn robot_removed(&self, name: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn tile(&self, name: String, x: i32, y: i32) -> zbus::Result<()>;
+}
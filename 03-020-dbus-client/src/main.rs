@@ -1,15 +1,27 @@
+mod cli;
+mod dbus;
 mod position;
 mod robot;
 mod tui;
 mod world;
 
+use clap::Parser;
+use cli::Cli;
+use dbus::{RobotRemovedStream, RobotStream, RustyProxy, TileStream};
+use futures_util::StreamExt;
 use position::Position;
-use robot::Robot;
+use robot::{Heading, Robot};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use world::{Tile, World};
+use world::{Belief, Tile, World};
+use zbus::fdo::ObjectManagerProxy;
 const H: i32 = 20;
 const W: i32 = 40;
+/// How often the belief map of the steered robot is fetched.
+const BELIEF_INTERVAL: Duration = Duration::from_millis(500);
+/// Interface of the robot objects the service exports.
+const ROBOT_INTERFACE: &str = "de.marc.rusty.Robot";
 
 pub async fn add_outer_wall(world: Arc<Mutex<World>>, width: i32, height: i32, wall_tile: Tile) {
     let mut world = world.lock().await;
@@ -30,44 +42,195 @@ pub async fn handle_movement(
     world: Arc<Mutex<World>>,
 ) {
     let mut rusty = rusty.lock().await;
-    let pos = &mut rusty.position;
-    let x = &mut pos.x;
-    let y = &mut pos.y;
 
+    // the world is drawn with y pointing up (north)
     match command {
-        tui::Command::MoveUp => {
-            if *y > 0 {
-                *y -= 1
+        tui::Command::Up => {
+            rusty.heading = Heading::North;
+            if rusty.position.y < H - 1 {
+                rusty.position.y += 1
             }
         }
-        tui::Command::MoveDown => {
-            if *y < H - 1 {
-                *y += 1
+        tui::Command::Down => {
+            rusty.heading = Heading::South;
+            if rusty.position.y > 0 {
+                rusty.position.y -= 1
             }
         }
-        tui::Command::MoveLeft => {
-            if *x > 0 {
-                *x -= 1
+        tui::Command::Left => {
+            rusty.heading = Heading::West;
+            if rusty.position.x > 0 {
+                rusty.position.x -= 1
             }
         }
-        tui::Command::MoveRight => {
-            if *x < W - 1 {
-                *x += 1
+        tui::Command::Right => {
+            rusty.heading = Heading::East;
+            if rusty.position.x < W - 1 {
+                rusty.position.x += 1
             }
         }
     }
 
-    world
-        .lock()
-        .await
-        .update_robot("Rusty", rusty.position.clone(), rusty.state_of_charge);
+    world.lock().await.update_robot(
+        &rusty.name,
+        rusty.position.clone(),
+        rusty.heading,
+        rusty.state_of_charge,
+    );
 }
 
-#[tokio::main]
-async fn main() -> std::io::Result<()> {
+/// Moves robot `name` with `MoveRobot`: up and down drive it forward and
+/// backwards, left and right turn it.
+///
+/// Relative moves do not depend on the robot's position in `world`, which
+/// lags behind the service until its signal arrives. A failed move is
+/// shown until the next one succeeds.
+pub async fn handle_remote_movement(
+    command: tui::Command,
+    proxy: RustyProxy<'static>,
+    name: String,
+    world: Arc<Mutex<World>>,
+) {
+    let direction = match command {
+        tui::Command::Up => "forward",
+        tui::Command::Down => "backwards",
+        tui::Command::Left => "turn_left",
+        tui::Command::Right => "turn_right",
+    };
+    let moved = proxy.move_robot(&name, direction, 1).await;
+    world.lock().await.message = moved.err().map(describe);
+}
+
+/// Short text of an error returned by the service, e.g. `Blocked: Wall at ((0/3))`.
+fn describe(error: zbus::Error) -> String {
+    match error {
+        zbus::Error::MethodError(name, Some(message), _) => {
+            let name = name.as_str();
+            let name = name.strip_prefix("de.marc.rusty.Error.").unwrap_or(name);
+            format!("{name}: {message}")
+        }
+        error => error.to_string(),
+    }
+}
+
+/// Mirrors the `robot`, `robot_removed` and `tile` signals of the service into `world`.
+async fn follow_signals(
+    mut robots: RobotStream,
+    mut removed: RobotRemovedStream,
+    mut tiles: TileStream,
+    world: Arc<Mutex<World>>,
+) -> zbus::Result<()> {
+    loop {
+        tokio::select! {
+            Some(signal) = robots.next() => {
+                let args = signal.args()?;
+                let heading = Heading::from_name(args.heading()).unwrap_or_default();
+                world.lock().await.set_robot_pose(args.name(), Position { x: args.x, y: args.y }, heading);
+            }
+            Some(signal) = removed.next() => {
+                let args = signal.args()?;
+                world.lock().await.remove_robot(args.name());
+            }
+            Some(signal) = tiles.next() => {
+                let args = signal.args()?;
+                if let Some(tile) = Tile::from_name(args.name()) {
+                    world.lock().await.add_tile(Position { x: args.x, y: args.y }, tile);
+                }
+            }
+            else => return Ok(()),
+        }
+    }
+}
+
+/// Keeps the belief map of robot `name` in `world` up to date.
+async fn poll_belief(
+    proxy: RustyProxy<'static>,
+    name: String,
+    world: Arc<Mutex<World>>,
+) -> zbus::Result<()> {
+    let mut interval = tokio::time::interval(BELIEF_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cells = proxy.get_belief_map(&name).await?;
+        world
+            .lock()
+            .await
+            .set_belief(cells.into_iter().filter_map(|(x, y, belief)| {
+                Some((Position { x, y }, Belief::from_name(&belief)?))
+            }));
+    }
+}
+
+/// Name, position and heading of every robot, read from the properties
+/// of the robot objects.
+async fn robot_poses(
+    connection: &zbus::Connection,
+) -> zbus::Result<Vec<(String, Position, Heading)>> {
+    let objects = ObjectManagerProxy::builder(connection)
+        .destination("de.marc.rusty")?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+    Ok(objects
+        .into_values()
+        .filter_map(|interfaces| {
+            let (_, properties) = interfaces
+                .into_iter()
+                .find(|(interface, _)| interface.as_str() == ROBOT_INTERFACE)?;
+            let name = String::try_from(properties.get("Name")?.try_clone().ok()?).ok()?;
+            let (x, y) = <(i32, i32)>::try_from(properties.get("Position")?.try_clone().ok()?).ok()?;
+            let heading = String::try_from(properties.get("Heading")?.try_clone().ok()?).ok()?;
+            let heading = Heading::from_name(&heading).unwrap_or_default();
+            Some((name, Position { x, y }, heading))
+        })
+        .collect())
+}
+
+async fn run_remote(name: String) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let proxy = RustyProxy::new(&connection).await?;
+    let world = Arc::new(Mutex::new(World::default()));
+
+    // subscribe first so no change between the initial fetch and the stream gets lost
+    let robots = proxy.receive_robot().await?;
+    let removed = proxy.receive_robot_removed().await?;
+    let tiles = proxy.receive_tile().await?;
+    let signals = tokio::spawn(follow_signals(robots, removed, tiles, world.clone()));
+
+    let width = proxy.width().await? as i32;
+    let height = proxy.height().await? as i32;
+    for (tile, x, y) in proxy.get_tiles().await? {
+        if let Some(tile) = Tile::from_name(&tile) {
+            world.lock().await.add_tile(Position { x, y }, tile);
+        }
+    }
+    if proxy.get_robot(&name).await.is_err() {
+        proxy.add_robot(&name).await?;
+    }
+    for (robot, position, heading) in robot_poses(&connection).await? {
+        world.lock().await.set_robot_pose(&robot, position, heading);
+    }
+
+    let belief = tokio::spawn(poll_belief(proxy.clone(), name.clone(), world.clone()));
+
+    let world_clone = world.clone();
+    let movement = move |command: tui::Command| {
+        handle_remote_movement(command, proxy.clone(), name.clone(), world_clone.clone())
+    };
+    let result = tokio::spawn(tui::tui(movement, width, height, world)).await;
+    signals.abort();
+    belief.abort();
+    result.map_err(std::io::Error::other)??;
+    Ok(())
+}
+
+async fn run_offline(name: String) -> std::io::Result<()> {
     let rusty = Arc::new(Mutex::new(Robot {
-        name: "Rusty".to_string(),
+        name,
         position: Position { x: 5i32, y: 5i32 },
+        heading: Heading::North,
         state_of_charge: 255,
     }));
     let rusty_clone = rusty.clone();
@@ -84,12 +247,22 @@ async fn main() -> std::io::Result<()> {
     world.lock().await.add_robot(
         rusty.name.clone(),
         rusty.position.clone(),
+        rusty.heading,
         rusty.state_of_charge,
     );
     drop(rusty);
 
     add_outer_wall(world.clone(), W, H, Tile::Wall).await;
 
-    let _ = tokio::spawn(tui::tui(movement, 40, 20, world)).await?;
-    Ok(())
+    tokio::spawn(tui::tui(movement, W, H, world)).await?
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    if cli.offline {
+        run_offline(cli.name).await
+    } else {
+        run_remote(cli.name).await.map_err(std::io::Error::other)
+    }
 }
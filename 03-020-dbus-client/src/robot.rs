@@ -1,7 +1,39 @@
 use crate::position::Position;
 
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Heading {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    /// Parses a heading as sent by the service, `"N"`, `"E"`, `"S"` or `"W"`.
+    pub fn from_name(name: &str) -> Option<Heading> {
+        match name {
+            "N" => Some(Heading::North),
+            "E" => Some(Heading::East),
+            "S" => Some(Heading::South),
+            "W" => Some(Heading::West),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            Heading::North => '^',
+            Heading::East => '>',
+            Heading::South => 'v',
+            Heading::West => '<',
+        }
+    }
+}
+
 pub struct Robot {
     pub name: String,
     pub position: Position,
+    pub heading: Heading,
     pub state_of_charge: u8,
 }
@@ -1,5 +1,5 @@
 use crate::position::Position;
-use crate::world::{Tile, World};
+use crate::world::{Belief, Tile, World};
 use crossterm::{
     event::{Event, EventStream, KeyCode},
     execute,
@@ -18,10 +18,10 @@ use tokio::{select, time};
 
 #[derive(Debug)]
 pub enum Command {
-    MoveUp,
-    MoveDown,
-    MoveLeft,
-    MoveRight,
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
 pub async fn tui<F, Fut>(
@@ -41,35 +41,56 @@ where
     let mut terminal = Terminal::new(backend)?;
 
     let mut reader = EventStream::new();
+    // show the belief map of the steered robot instead of the true tiles
+    let mut overlay = false;
 
     'draw: loop {
         let world = world.lock().await;
         terminal.draw(|f| {
             let area = f.area();
             let mut buf = String::new();
-            for y in 0..height {
+            // top row is the northmost one
+            for y in (0..height).rev() {
                 for x in 0..width {
-                    if world
+                    if let Some(robot) = world
                         .robots
                         .iter()
                         .find(|robot| robot.position == Position { x, y })
-                        .is_some()
                     {
-                        buf.push('R');
+                        buf.push(robot.heading.glyph());
                         continue;
                     }
-                    let tile = match world.tiles.get(&Position { x, y }) {
-                        Some(Tile::Wall) => 'W',
-                        Some(Tile::ChargePad) => 'C',
-                        _ => ' ',
+                    let position = Position { x, y };
+                    let tile = if overlay {
+                        match world.belief.get(&position) {
+                            Some(Belief::Occupied) => '#',
+                            Some(Belief::Free) => '.',
+                            None => ' ',
+                        }
+                    } else {
+                        match world.tiles.get(&position) {
+                            Some(Tile::Wall) => 'W',
+                            Some(Tile::ChargePad) => 'C',
+                            // like in the text maps of the service
+                            Some(Tile::Door { open: true }) => '/',
+                            Some(Tile::Door { open: false }) => '+',
+                            _ => ' ',
+                        }
                     };
                     buf.push(tile);
                 }
                 buf.push('\n');
             }
+            let message = world.message.clone().unwrap_or_default();
             drop(world); // give back the lock asap
+            let title = if overlay {
+                "Belief map (press m for the world, q to exit)"
+            } else {
+                "Rusty World (press m for the belief map, q to exit)"
+            };
             let block = Block::default()
-                .title("Rusty World (press q to exit)")
+                .title(title)
+                .title_bottom(message)
                 .borders(Borders::ALL);
             f.render_widget(Paragraph::new(buf).block(block), area);
         })?;
@@ -78,10 +99,11 @@ where
             maybe_event = reader.next() => {
                 if let Some(Ok(Event::Key(key))) = maybe_event {
                     match key.code {
-                        KeyCode::Up => movement(Command::MoveUp).await,
-                        KeyCode::Down => movement(Command::MoveDown).await,
-                        KeyCode::Left => movement(Command::MoveLeft).await,
-                        KeyCode::Right => movement(Command::MoveRight).await,
+                        KeyCode::Up => movement(Command::Up).await,
+                        KeyCode::Down => movement(Command::Down).await,
+                        KeyCode::Left => movement(Command::Left).await,
+                        KeyCode::Right => movement(Command::Right).await,
+                        KeyCode::Char('m') => overlay = !overlay,
                         KeyCode::Char('q') | KeyCode::Esc => break 'draw,
                         _ => {}
                     }
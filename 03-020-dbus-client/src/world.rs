@@ -1,17 +1,50 @@
 use crate::position::Position;
-use crate::robot::Robot;
+use crate::robot::{Heading, Robot};
+use serde::Deserialize;
 
-#[derive(Clone)]
+#[derive(Clone, Deserialize)]
 pub enum Tile {
     Empty,
     Wall,
     ChargePad,
+    /// The service also sends the door's period, which is of no interest here.
+    Door { open: bool },
+}
+
+impl Tile {
+    /// Parses a tile as sent by the service, JSON like `"Wall"` (with quotes)
+    /// or `{"Door":{"open":false,"period":4}}`.
+    pub fn from_name(name: &str) -> Option<Tile> {
+        serde_json::from_str(name).ok()
+    }
+}
+
+/// What the steered robot believes about a cell it has observed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Belief {
+    Free,
+    Occupied,
+}
+
+impl Belief {
+    /// Parses a belief as sent by the service, `"free"` or `"occupied"`.
+    pub fn from_name(name: &str) -> Option<Belief> {
+        match name {
+            "free" => Some(Belief::Free),
+            "occupied" => Some(Belief::Occupied),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct World {
     pub tiles: std::collections::HashMap<Position, Tile>,
     pub robots: Vec<Robot>,
+    /// Belief map of the steered robot, cells missing are unknown to it.
+    pub belief: std::collections::HashMap<Position, Belief>,
+    /// Why the last move of the steered robot failed, if it did.
+    pub message: Option<String>,
 }
 
 impl World {
@@ -19,21 +52,53 @@ impl World {
         self.tiles.insert(position, tile);
     }
 
-    pub fn add_robot(&mut self, name: String, position: Position, state_of_charge: u8) {
+    pub fn add_robot(
+        &mut self,
+        name: String,
+        position: Position,
+        heading: Heading,
+        state_of_charge: u8,
+    ) {
         self.robots.push(Robot {
             name,
             position,
+            heading,
             state_of_charge,
         });
     }
 
-    pub fn update_robot(&mut self, name: &str, position: Position, state_of_charge: u8) {
+    pub fn update_robot(
+        &mut self,
+        name: &str,
+        position: Position,
+        heading: Heading,
+        state_of_charge: u8,
+    ) {
+        if let Some(robot) = self.robots.iter_mut().find(|robot| robot.name == name) {
+            robot.position = position;
+            robot.heading = heading;
+            robot.state_of_charge = state_of_charge;
+        }
+    }
+
+    /// Moves the robot `name` to `position` and turns it to `heading`,
+    /// adding it if it is not known yet.
+    pub fn set_robot_pose(&mut self, name: &str, position: Position, heading: Heading) {
         match self.robots.iter_mut().find(|robot| robot.name == name) {
             Some(robot) => {
                 robot.position = position;
-                robot.state_of_charge = state_of_charge;
+                robot.heading = heading;
             }
-            _ => {}
+            None => self.add_robot(name.to_string(), position, heading, 255),
         }
     }
+
+    /// Replaces the belief map with `cells`.
+    pub fn set_belief(&mut self, cells: impl IntoIterator<Item = (Position, Belief)>) {
+        self.belief = cells.into_iter().collect();
+    }
+
+    pub fn remove_robot(&mut self, name: &str) {
+        self.robots.retain(|robot| robot.name != name);
+    }
 }